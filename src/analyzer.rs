@@ -1,14 +1,389 @@
-use crate::github::GitHubClient;
+use crate::error::DisforkError;
+use crate::github::{Comparison, GitHubApi, GitHubClient};
 use anyhow::{Result, anyhow};
-use octocrab::models::Repository;
+use octocrab::models::{Repository, repos::Branch};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OnceCell, Semaphore, mpsc};
+use tokio::task::JoinSet;
+
+/// Max gap between fork and upstream pushes for the fork to count as a mirror
+const MIRROR_TOLERANCE_SECS: i64 = 15 * 60;
+
+/// Upstream branches listed for the head SHA pre-check, so huge upstreams don't cost
+/// many pages of branches
+const UPSTREAM_BRANCH_LIMIT: usize = 300;
+
+/// Forks with unique work that weren't pushed to for this long are stale
+const STALE_AFTER_DAYS: i64 = 365;
+
+/// What the analysis concluded about a fork
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Nothing upstream doesn't have, safe to delete
+    Useless,
+    /// Has unique work, but wasn't pushed to in a long time
+    Stale,
+    /// Has unique work
+    Active,
+    /// No unique work going by its branches, but kept, usually by a [`KeepRule`]
+    Protected,
+    /// The upstream is gone, the fork may be the only copy
+    Orphaned,
+    /// Couldn't be analyzed, needs manual review
+    Unknown,
+}
+
+impl Verdict {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Useless => "useless",
+            Self::Stale => "stale",
+            Self::Active => "active",
+            Self::Protected => "kept",
+            Self::Orphaned => "orphaned",
+            Self::Unknown => "needs review",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForkKind {
+    Regular,
+    /// No branches at all
+    Empty,
+    /// Marked as a fork, but GitHub returned neither `parent` nor `source`, or the
+    /// upstream was deleted or taken down
+    Orphaned,
+    /// A single branch sitting at upstream's default branch head, and no tags
+    Pristine,
+    /// Not analyzed because of too many branches, `truncated` if there are even more than `count`
+    TooManyBranches {
+        count: usize,
+        truncated: bool,
+    },
+    /// The parent is the fork itself, comparing would be meaningless
+    Anomalous,
+    /// Nothing ahead of upstream and pushed in lockstep with it, likely synced by automation
+    Mirror,
+    /// Judged by push timestamps alone with [`AnalyzerOptions::fast`]
+    Triaged,
+}
 
 #[derive(Debug, Clone)]
 pub struct ForkInfo {
     pub repo: Repository,
-    pub is_useless: bool,
+    pub verdict: Verdict,
+    pub kind: ForkKind,
+    /// Default branch compared to upstream's, `None` if not analyzed
+    pub divergence: Option<Comparison>,
+    /// Root of the fork network, set when other scanned forks share it
+    pub network: Option<String>,
+    /// The parent belongs to the fork's owner or another account of the user
+    pub is_self_fork: bool,
+    /// Number of branches listed, at least this many with [`ForkKind::TooManyBranches`]
+    pub branch_count: Option<usize>,
+    /// Branches as listed for the analysis, `None` if they weren't listed
+    pub branches: Option<Vec<Branch>>,
+    /// Set when only some of the branches were compared
+    pub branch_sample: Option<BranchSample>,
+    /// Comparison of each compared branch, all of them with
+    /// [`AnalyzerOptions::detailed_branches`]. Only exported then, otherwise it's for
+    /// the branch counts shown
+    pub branch_results: Option<Vec<BranchResult>>,
+    /// Comparing stopped at the first branch ahead, other branches may be ahead too
+    pub branch_results_partial: bool,
+    /// Some compared branch has commits upstream lacks, or couldn't be compared
+    pub has_commits_ahead: bool,
+    /// Set when a policy overrode the analysis finding the fork useless
+    pub kept_by: Option<KeepRule>,
+    /// Sum of commits ahead over all branches, only with [`AnalyzerOptions::total_ahead`].
+    /// An approximate upper bound, as commits shared by several branches count for each
+    /// of them, while branches missing upstream don't count at all
+    pub total_ahead: Option<i64>,
+    /// The most recently pushed of the other scanned forks of the same parent, set by
+    /// [`flag_duplicates`]
+    pub duplicate_of: Option<String>,
+}
+
+/// A fork branch compared to the upstream branch of the same name (or upstream's
+/// default branch for the fork's default branch and branches upstream lacks)
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchResult {
+    pub name: String,
+    pub ahead: Option<i64>,
+    pub behind: Option<i64>,
+    /// `false` if upstream has no such branch, it was compared to upstream's default
+    /// branch if possible
+    pub exists_upstream: bool,
+}
+
+impl BranchResult {
+    fn compared(name: &str, comparison: &Comparison) -> Self {
+        Self {
+            name: name.to_string(),
+            ahead: Some(comparison.ahead_by),
+            behind: Some(comparison.behind_by),
+            exists_upstream: true,
+        }
+    }
+}
+
+/// Policy that kept a fork the analysis found useless
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeepRule {
+    SelfFork,
+    SourceOnly,
+    ArchivedUpstream,
+    Topic,
+    ProtectedBranch,
+    Deployments,
+    OpenPullRequest,
+    Starred,
+    Releases,
+    Pages,
+    RecentPush,
+    Submodule,
+    Automation,
+    ActionsConfig,
+    Wiki,
+    Template,
+    Pinned,
+    Packages,
+    DownstreamForks,
+    Script,
+    IssueActivity,
+    CiActivity,
+    Codespaces,
+    /// Kept as the one fork of its network with --dedupe-network
+    NetworkRepresentative,
+    /// Kept as the most recently pushed of several forks of the same parent
+    NewestDuplicate,
+    /// A keep check failed, so whether another rule applies is unknown
+    CheckFailed,
+}
+
+impl KeepRule {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::SelfFork => "fork of your own repo",
+            Self::SourceOnly => "network source not matching --source-only",
+            Self::ArchivedUpstream => "kept by archived upstream rule",
+            Self::Topic => "tagged with an excluded topic",
+            Self::ProtectedBranch => "default branch is protected",
+            Self::Deployments => "has deployments",
+            Self::OpenPullRequest => "a branch backs an open pull request",
+            Self::Starred => "starred or watched by others",
+            Self::Releases => "has its own releases or tags",
+            Self::Pages => "serves a GitHub Pages site",
+            Self::RecentPush => "pushed to recently",
+            Self::Submodule => "used as a submodule by another of your repos",
+            Self::Automation => "in use by automation (webhooks or deploy keys)",
+            Self::ActionsConfig => "has Actions secrets, variables or environments",
+            Self::Wiki => "has wiki pages, which upstream doesn't carry",
+            Self::Template => "template repository",
+            Self::Pinned => "pinned on the profile",
+            Self::Packages => "packages were published from it",
+            Self::DownstreamForks => "has downstream forks",
+            Self::Script => "kept by the rule script",
+            Self::IssueActivity => "has open issues or discussions",
+            Self::CiActivity => "workflows ran recently",
+            Self::Codespaces => "has codespaces",
+            Self::NetworkRepresentative => "kept as the only fork of its network",
+            Self::NewestDuplicate => "newest of several forks of the same upstream",
+            Self::CheckFailed => "a keep check failed, kept to be safe",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BranchSample {
+    pub examined: usize,
+    pub total: usize,
 }
 
 impl ForkInfo {
+    fn new(
+        repo: Repository,
+        is_useless: bool,
+        kind: ForkKind,
+        divergence: Option<Comparison>,
+    ) -> Self {
+        let verdict = match kind {
+            _ if is_useless => Verdict::Useless,
+            ForkKind::Orphaned => Verdict::Orphaned,
+            ForkKind::Anomalous | ForkKind::TooManyBranches { .. } => Verdict::Unknown,
+            // Nothing upstream lacks, so not Stale or Active even if not deleted
+            ForkKind::Empty | ForkKind::Pristine | ForkKind::Mirror => Verdict::Protected,
+            ForkKind::Regular | ForkKind::Triaged => {
+                let stale_before = chrono::Utc::now() - chrono::TimeDelta::days(STALE_AFTER_DAYS);
+                match repo.pushed_at {
+                    Some(pushed) if pushed < stale_before => Verdict::Stale,
+                    _ => Verdict::Active,
+                }
+            }
+        };
+        Self {
+            repo,
+            verdict,
+            kind,
+            divergence,
+            network: None,
+            is_self_fork: false,
+            branch_count: None,
+            branches: None,
+            branch_sample: None,
+            total_ahead: None,
+            kept_by: None,
+            branch_results: None,
+            branch_results_partial: false,
+            has_commits_ahead: false,
+            duplicate_of: None,
+        }
+    }
+
+    pub fn is_useless(&self) -> bool {
+        self.verdict == Verdict::Useless
+    }
+
+    /// Keeps a useless fork, recording the rule responsible
+    pub fn keep(&mut self, rule: KeepRule) {
+        if self.is_useless() {
+            self.verdict = Verdict::Protected;
+            self.kept_by = Some(rule);
+        }
+    }
+
+    /// Why the fork got its verdict, the rule keeping it first
+    pub fn reasons(&self) -> Vec<String> {
+        let mut reasons = Vec::new();
+        if let Some(rule) = self.kept_by {
+            reasons.push(rule.describe().to_string());
+        }
+
+        let found_useless = matches!(self.verdict, Verdict::Useless | Verdict::Protected);
+        let reason = match self.kind {
+            ForkKind::Empty => "no branches".to_string(),
+            ForkKind::Pristine => "never changed since forking".to_string(),
+            ForkKind::Mirror => "mirror kept in sync with upstream".to_string(),
+            ForkKind::TooManyBranches { .. } => "too many branches to analyze".to_string(),
+            ForkKind::Orphaned => "upstream gone, may be the only copy".to_string(),
+            ForkKind::Anomalous => "anomalous metadata, needs review".to_string(),
+            ForkKind::Triaged if found_useless => {
+                "not pushed to since forking or since upstream's last push".to_string()
+            }
+            ForkKind::Triaged => "pushed to since forking and upstream's last push".to_string(),
+            ForkKind::Regular if found_useless => "nothing upstream doesn't have".to_string(),
+            ForkKind::Regular => match self.divergence.as_ref().map(|d| d.ahead_by) {
+                Some(ahead) if ahead > 0 => format!("default branch {} commits ahead", ahead),
+                _ => "a branch has commits upstream doesn't".to_string(),
+            },
+        };
+        reasons.push(reason);
+        if self.verdict == Verdict::Stale {
+            reasons.push(format!("not pushed to in over {} days", STALE_AFTER_DAYS));
+        }
+        reasons
+    }
+
+    /// Why the fork is kept, `None` if it's useless
+    pub fn keep_reason(&self) -> Option<String> {
+        if self.is_useless() {
+            return None;
+        }
+        self.reasons().into_iter().next()
+    }
+
+    /// `false` for the empty repos listed with `--include-empty`
+    pub fn is_fork(&self) -> bool {
+        self.repo.fork.unwrap_or(false)
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.repo.archived.unwrap_or(false)
+    }
+
+    pub fn is_template(&self) -> bool {
+        self.repo.is_template.unwrap_or(false)
+    }
+
+    /// Open issues and pull requests, `0` with issues disabled
+    pub fn open_issues(&self) -> u32 {
+        match self.repo.has_issues {
+            Some(true) => self.repo.open_issues_count.unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    pub fn stars(&self) -> u32 {
+        self.repo.stargazers_count.unwrap_or(0)
+    }
+
+    /// Watchers besides the owner, who watches their own repos by default
+    pub fn watchers(&self) -> u32 {
+        self.repo
+            .subscribers_count
+            .and_then(|count| u32::try_from(count).ok())
+            .unwrap_or(0)
+            .saturating_sub(1)
+    }
+
+    /// Number of compared branches ahead of upstream and the most commits any of them
+    /// is ahead, `None` if no branch is ahead. Lower bounds if
+    /// [`Self::branch_results_partial`]
+    pub fn branches_ahead(&self) -> Option<(usize, i64)> {
+        let ahead: Vec<i64> = self
+            .branch_results
+            .iter()
+            .flatten()
+            .filter_map(|branch| branch.ahead)
+            .filter(|&ahead| ahead > 0)
+            .collect();
+        Some((ahead.len(), ahead.iter().copied().max()?))
+    }
+
+    /// Branches besides the default one without commits upstream lacks, i.e. merged
+    /// into or even with upstream
+    pub fn prunable_branches(&self) -> Vec<&str> {
+        self.branch_results
+            .iter()
+            .flatten()
+            .filter(|branch| branch.ahead == Some(0))
+            .map(|branch| branch.name.as_str())
+            .filter(|&name| Some(name) != self.repo.default_branch.as_deref())
+            .collect()
+    }
+
+    /// Commits ahead of upstream, over all branches if summed up
+    pub fn ahead_by(&self) -> i64 {
+        self.total_ahead
+            .or(self.divergence.as_ref().map(|d| d.ahead_by))
+            .unwrap_or(0)
+    }
+
+    pub fn html_url(&self) -> Option<&str> {
+        self.repo.html_url.as_ref().map(|url| url.as_str())
+    }
+
+    pub fn parent_html_url(&self) -> Option<&str> {
+        self.repo
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.html_url.as_ref())
+            .map(|url| url.as_str())
+    }
+
+    pub fn parent_full_name(&self) -> Option<&str> {
+        self.repo
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.full_name.as_deref())
+    }
+
     pub fn full_name(&self) -> &str {
         self.repo
             .full_name
@@ -19,58 +394,1028 @@ impl ForkInfo {
     pub fn owner_login(&self) -> Option<&str> {
         self.repo.owner.as_ref().map(|owner| owner.login.as_str())
     }
+
+    pub fn parent_owner_login(&self) -> Option<&str> {
+        self.repo
+            .parent
+            .as_ref()
+            .and_then(|parent| parent.owner.as_ref())
+            .map(|owner| owner.login.as_str())
+    }
+
+    /// Rough weight of what would be lost by deleting the fork, higher is riskier
+    pub fn risk_score(&self) -> f64 {
+        let stars = self.repo.stargazers_count.unwrap_or(0) as f64;
+        let downstream = self.repo.forks_count.unwrap_or(0) as f64;
+        let size_mib = self.repo.size.unwrap_or(0) as f64 / 1024.0;
+        let days_since_push = self
+            .repo
+            .pushed_at
+            .map(|pushed| (chrono::Utc::now() - pushed).num_days().max(0) as f64)
+            .unwrap_or(f64::INFINITY);
+
+        stars * 10.0 + downstream * 20.0 + size_mib + 100.0 / (1.0 + days_since_push / 30.0)
+    }
+
+    /// Full name of the repository at the root of the fork network
+    pub fn network_root(&self) -> Option<&str> {
+        self.repo
+            .source
+            .as_ref()
+            .or(self.repo.parent.as_ref())
+            .and_then(|root| root.full_name.as_deref())
+    }
+}
+
+/// Orders forks so the likely useless ones are analyzed first: empty, then archived,
+/// then least recently pushed, smallest first among equals
+pub fn prioritize(forks: &mut [Repository]) {
+    forks.sort_by_key(|repo| {
+        (
+            repo.size.unwrap_or(0) > 0,
+            !repo.archived.unwrap_or(false),
+            repo.pushed_at,
+            repo.size.unwrap_or(0),
+        )
+    });
 }
 
+/// Keeps the fork by `rule` if the keep check found it applies, or to be safe if the
+/// check failed. Only rate limits and auth failures abort the analysis, every other check
+/// would fail the same way.
+fn apply_keep_check<E: Into<anyhow::Error>>(
+    info: &mut ForkInfo,
+    rule: KeepRule,
+    found: std::result::Result<bool, E>,
+) -> Result<()> {
+    match found.map_err(Into::into) {
+        Ok(true) => info.keep(rule),
+        Ok(false) => {}
+        Err(e)
+            if e.chain().any(|cause| {
+                matches!(
+                    cause.downcast_ref::<DisforkError>(),
+                    Some(DisforkError::RateLimited { .. } | DisforkError::Auth(_))
+                )
+            }) =>
+        {
+            return Err(e);
+        }
+        Err(e) => {
+            tracing::warn!(repo = info.full_name(), ?rule, error = %e, "keep check failed");
+            info.keep(KeepRule::CheckFailed);
+        }
+    }
+    Ok(())
+}
+
+/// Whether any of `topics` is one of `wanted`, ignoring case
+pub fn has_any_topic(topics: &[String], wanted: &[String]) -> bool {
+    topics
+        .iter()
+        .any(|topic| wanted.iter().any(|w| w.eq_ignore_ascii_case(topic)))
+}
+
+/// Keeps the forks in `full_names`, which are lowercase
+pub fn keep_listed(fork_infos: &mut [ForkInfo], full_names: &HashSet<String>, rule: KeepRule) {
+    for info in fork_infos {
+        if full_names.contains(&info.full_name().to_lowercase()) {
+            info.keep(rule);
+        }
+    }
+}
+
+/// Keeps the forks an account-wide lookup listed, or every fork to be safe if the lookup
+/// failed. Like [`apply_keep_check`], only rate limits and auth failures abort.
+pub fn keep_looked_up(
+    fork_infos: &mut [ForkInfo],
+    lookup: std::result::Result<Vec<String>, DisforkError>,
+    rule: KeepRule,
+) -> Result<()> {
+    match lookup {
+        Ok(full_names) => keep_listed(fork_infos, &full_names.into_iter().collect(), rule),
+        Err(e @ (DisforkError::RateLimited { .. } | DisforkError::Auth(_))) => {
+            return Err(e.into());
+        }
+        Err(e) => {
+            tracing::warn!(?rule, error = %e, "keep lookup failed, keeping every fork");
+            for info in fork_infos {
+                info.keep(KeepRule::CheckFailed);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Indices of the forks grouped by the repo `upstream` returns for them, compared
+/// ignoring case like GitHub does. Forks without one are left out.
+fn group_by_upstream<'a>(
+    fork_infos: &'a [ForkInfo],
+    upstream: impl Fn(&'a ForkInfo) -> Option<&'a str>,
+) -> Vec<Vec<usize>> {
+    let mut groups: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, info) in fork_infos.iter().enumerate() {
+        if let Some(name) = upstream(info) {
+            groups.entry(name.to_lowercase()).or_default().push(i);
+        }
+    }
+    groups.into_values().collect()
+}
+
+/// The most recently pushed of the forks at `members`
+fn newest(fork_infos: &[ForkInfo], members: &[usize]) -> Option<usize> {
+    members
+        .iter()
+        .copied()
+        .max_by_key(|&i| fork_infos[i].repo.pushed_at)
+}
+
+/// Keeps the most recently pushed of several useless forks of the same parent, e.g.
+/// after re-forking, so only the older duplicates stay useless
+pub fn keep_newest_duplicates(fork_infos: &mut [ForkInfo]) {
+    for members in group_by_upstream(fork_infos, ForkInfo::parent_full_name) {
+        if members.len() < 2 || members.iter().any(|&i| !fork_infos[i].is_useless()) {
+            continue;
+        }
+        if let Some(newest) = newest(fork_infos, &members) {
+            fork_infos[newest].keep(KeepRule::NewestDuplicate);
+        }
+    }
+}
+
+/// Points each of several forks of the same parent, e.g. in an org and a user account,
+/// to the most recently pushed of them as the one worth keeping. Only flags them,
+/// verdicts are left alone.
+pub fn flag_duplicates(fork_infos: &mut [ForkInfo]) {
+    for members in group_by_upstream(fork_infos, ForkInfo::parent_full_name) {
+        if members.len() < 2 {
+            continue;
+        }
+        let Some(newest) = newest(fork_infos, &members) else {
+            continue;
+        };
+        let newest_name = fork_infos[newest].full_name().to_string();
+        for i in members.into_iter().filter(|&i| i != newest) {
+            fork_infos[i].duplicate_of = Some(newest_name.clone());
+        }
+    }
+}
+
+/// Keeps one fork of each fork network, so only duplicates stay useless.
+///
+/// A network where every fork is useless keeps its most recently pushed fork.
+pub fn dedupe_network(fork_infos: &mut [ForkInfo]) {
+    for members in group_by_upstream(fork_infos, ForkInfo::network_root) {
+        if members.len() > 1 {
+            let root = fork_infos[members[0]].network_root().map(String::from);
+            for &i in &members {
+                fork_infos[i].network = root.clone();
+            }
+        }
+
+        if members.iter().any(|&i| !fork_infos[i].is_useless()) {
+            continue;
+        }
+        if let Some(keep) = newest(fork_infos, &members) {
+            fork_infos[keep].keep(KeepRule::NetworkRepresentative);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AnalyzerOptions {
+    /// Skip analyzing repos with more than this many branches, unless
+    /// [`Self::limit_branches_per_fork`] is set
+    pub max_branches: usize,
+    /// Only compare the default branch and this many most recently committed branches,
+    /// instead of skipping repos with too many branches
+    pub limit_branches_per_fork: Option<usize>,
+    /// Compare every branch to sum up commits ahead, instead of stopping at the first
+    /// branch ahead
+    pub total_ahead: bool,
+    /// Reuse comparisons of the same head commit against the same upstream base
+    pub compare_cache: bool,
+    /// Forks only ahead on their default branch are useless, their default branch
+    /// commits are lost on deletion
+    pub ignore_default_branch_only_ahead: bool,
+    /// Branches ahead only by commits a merged pull request landed upstream (squashed or
+    /// rebased) don't count as ahead
+    pub detect_squash_merged: bool,
+    /// Commits by bots don't count as ahead
+    pub ignore_bot_commits: bool,
+    /// Judge forks by push timestamps alone, without listing or comparing branches
+    pub fast: bool,
+    /// Compare every branch instead of stopping at the first one ahead
+    pub detailed_branches: bool,
+    /// Max concurrent repo lookups while fetching parents before the analysis
+    pub parent_fetch_concurrency: usize,
+    /// Max concurrent compare requests per fork, so branch-heavy forks can't hog the
+    /// global request permits and stall other forks' analyses. Trades total run time for
+    /// it, the last branch-heavy forks can't use every permit. `None` for no cap.
+    pub per_fork_compare_limit: Option<usize>,
+    /// Only these branches decide usefulness: the fork is useless if each of them
+    /// exists upstream with nothing ahead, regardless of any other branch. Takes
+    /// precedence over [`Self::ignore_branches`].
+    pub require_merged: Vec<String>,
+    /// Branches matching any of these globs don't count, whether ahead or not. Doesn't
+    /// apply to [`Self::require_merged`], whose branches always count.
+    pub ignore_branches: Vec<String>,
+    /// Only forks of archived upstreams may be useless
+    pub only_archived_upstream: bool,
+    /// Forks of archived upstreams are never useless
+    pub keep_archived_upstream: bool,
+    /// Compare to the immediate parent instead of the network source
+    pub prefer_parent: bool,
+    /// Forks tagged with any of these topics are never useless
+    pub exclude_topics: Vec<String>,
+    /// Forks with a protected default branch are never useless
+    pub keep_if_default_branch_protected: bool,
+    /// Forks pushed to within this many days are never useless
+    pub keep_recent: Option<u32>,
+    /// Forks with more stars or watchers than this are never useless
+    pub keep_starred_over: Option<u32>,
+    /// Don't keep forks for their own releases or tags
+    pub ignore_releases: bool,
+    /// Forks with workflow runs within this many days are never useless
+    pub ci_activity_days: Option<u32>,
+    /// Forks with any deployment are never useless
+    pub keep_with_deployments: bool,
+    /// Other accounts of the user, forks of their repos count as self-forks
+    pub own_accounts: Vec<String>,
+    /// Only forks whose network source is owned by this account may be useless
+    pub source_only: Option<String>,
+    /// Allow forks with forks of their own to be useless
+    pub include_forked: bool,
+    /// Allow self-forks to be useless
+    pub include_self_forks: bool,
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+fn glob_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => name.strip_prefix(prefix).is_some_and(|name| {
+            (0..=name.len())
+                .filter(|&i| name.is_char_boundary(i))
+                .any(|i| glob_match(rest, &name[i..]))
+        }),
+    }
+}
+
+/// Comparisons by upstream repo, base ref and head commit SHA
+type CompareCache = Mutex<HashMap<(String, String, String), Comparison>>;
+
+/// Values fetched once per key. Concurrent lookups of a key wait for the fetch already
+/// in flight, so forks analyzed at once don't each fetch the same upstream data.
+struct Coalesced<T>(Mutex<HashMap<String, Arc<OnceCell<T>>>>);
+
+impl<T> Default for Coalesced<T> {
+    fn default() -> Self {
+        Self(Mutex::default())
+    }
+}
+
+impl<T: Clone> Coalesced<T> {
+    /// The value for `key`, fetched unless already there. A failed fetch isn't cached,
+    /// the next lookup tries again.
+    async fn get_or_try_fetch<E, F, Fut>(&self, key: String, fetch: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let cell = self
+            .0
+            .lock()
+            .expect("coalesced cache poisoned")
+            .entry(key)
+            .or_default()
+            .clone();
+        cell.get_or_try_init(fetch).await.cloned()
+    }
+
+    /// The value for `key`, fetched unless already there
+    async fn get_or_fetch<F, Fut>(&self, key: String, fetch: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let cell = self
+            .0
+            .lock()
+            .expect("coalesced cache poisoned")
+            .entry(key)
+            .or_default()
+            .clone();
+        cell.get_or_init(fetch).await.clone()
+    }
+}
+
+/// Upstream repos by lowercase full name, `None` if the upstream doesn't exist anymore
+type Upstreams = Coalesced<Option<Repository>>;
+
+/// Head labels of open pull requests by lowercase upstream full name
+type OpenPulls = Coalesced<Arc<Vec<String>>>;
+
+/// Tag commit SHAs by tag name, by lowercase upstream full name
+type UpstreamTags = Coalesced<Arc<HashMap<String, String>>>;
+
+/// Branch head SHAs by branch name, by lowercase upstream full name
+type UpstreamHeads = Coalesced<Arc<HashMap<String, String>>>;
+
 #[derive(Clone)]
-pub struct ForkAnalyzer {
-    client: GitHubClient,
-    max_branches: usize,
+pub struct ForkAnalyzer<C = GitHubClient> {
+    client: C,
+    options: AnalyzerOptions,
+    /// Lives as long as the analyzer, so a fresh one is used for every run
+    compare_cache: Option<Arc<CompareCache>>,
+    /// Shared by all forks, so each upstream is only looked up once
+    upstreams: Arc<Upstreams>,
+    open_pulls: Arc<OpenPulls>,
+    upstream_tags: Arc<UpstreamTags>,
+    upstream_heads: Arc<UpstreamHeads>,
 }
 
-impl ForkAnalyzer {
-    pub fn new(client: GitHubClient, max_branches: usize) -> Self {
-        Self { 
+impl<C: GitHubApi> ForkAnalyzer<C> {
+    pub fn new(client: C, options: AnalyzerOptions) -> Self {
+        let compare_cache = options.compare_cache.then(Arc::default);
+        Self {
             client,
-            max_branches,
+            options,
+            compare_cache,
+            upstreams: Arc::default(),
+            open_pulls: Arc::default(),
+            upstream_tags: Arc::default(),
+            upstream_heads: Arc::default(),
         }
     }
 
+    /// Fetches the full repo of each fork, which carries `parent` and `source`, and the
+    /// upstream repos the analysis will look up, deduped by full name.
+    ///
+    /// Gathering this metadata up front keeps the analysis phase to branches and compares.
+    pub async fn fetch_parents(&self, forks: Vec<Repository>) -> Result<Vec<Repository>> {
+        let permits = Arc::new(Semaphore::new(self.options.parent_fetch_concurrency));
+
+        let mut tasks = JoinSet::new();
+        for (i, fork) in forks.into_iter().enumerate() {
+            let client = self.client.clone();
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits
+                    .acquire()
+                    .await
+                    .expect("parent fetch semaphore is never closed");
+                let owner = fork
+                    .owner
+                    .as_ref()
+                    .map(|o| o.login.as_str())
+                    .ok_or_else(|| anyhow!("Fork repository missing owner information"))?;
+                let repo = client.get_repo(owner, &fork.name).await?;
+                anyhow::Ok((i, repo))
+            });
+        }
+        let mut forks = Vec::with_capacity(tasks.len());
+        while let Some(result) = tasks.join_next().await {
+            forks.push(result??);
+        }
+        forks.sort_by_key(|(i, _)| *i);
+        let forks: Vec<_> = forks.into_iter().map(|(_, repo)| repo).collect();
+
+        // Embedded upstreams lack nothing the analysis needs unless they have to be
+        // looked up again
+        let needs_upstreams =
+            self.options.only_archived_upstream || self.options.keep_archived_upstream;
+        if !needs_upstreams {
+            return Ok(forks);
+        }
+
+        let mut upstreams = HashMap::new();
+        for upstream in forks.iter().filter_map(|fork| self.upstream_of(fork)) {
+            if let Some(owner) = &upstream.owner {
+                let key = format!("{}/{}", owner.login, upstream.name).to_lowercase();
+                upstreams
+                    .entry(key)
+                    .or_insert((owner.login.clone(), upstream.name.clone()));
+            }
+        }
+
+        let mut tasks = JoinSet::new();
+        for (owner, name) in upstreams.into_values() {
+            let analyzer = self.clone();
+            let permits = permits.clone();
+            tasks.spawn(async move {
+                let _permit = permits
+                    .acquire()
+                    .await
+                    .expect("parent fetch semaphore is never closed");
+                analyzer.upstream(&owner, &name).await
+            });
+        }
+        while let Some(result) = tasks.join_next().await {
+            result??;
+        }
+
+        Ok(forks)
+    }
+
+    /// Whether any open pull request to the fork's upstream, or to any other repo, comes
+    /// from the fork, whoever opened it
+    async fn backs_open_pull(&self, info: &ForkInfo) -> Result<bool> {
+        if self.backs_open_upstream_pull(info).await? {
+            return Ok(true);
+        }
+
+        // Pull requests elsewhere in the network can only be found by head commit
+        let Some(owner) = info.owner_login() else {
+            return Ok(false);
+        };
+        // The branches listed for the analysis, only listed here with --fast
+        let listed;
+        let branches = match &info.branches {
+            Some(branches) => branches,
+            None => {
+                listed = self
+                    .client
+                    .list_branches(owner, &info.repo.name, self.options.max_branches)
+                    .await?
+                    .items;
+                &listed
+            }
+        };
+        let mut checked = HashSet::new();
+        for sha in branches.iter().map(|branch| branch.commit.sha.as_str()) {
+            if checked.insert(sha)
+                && self
+                    .client
+                    .has_open_pull_from(owner, &info.repo.name, sha)
+                    .await?
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether any open pull request to the fork's upstream comes from the fork. An
+    /// account can only have one fork per network, so its login identifies the fork.
+    async fn backs_open_upstream_pull(&self, info: &ForkInfo) -> Result<bool> {
+        let (Some(owner), Some(upstream)) = (
+            info.owner_login(),
+            info.repo.parent.as_ref().or(info.repo.source.as_ref()),
+        ) else {
+            return Ok(false);
+        };
+        let Some(upstream_owner) = upstream.owner.as_ref().map(|o| o.login.as_str()) else {
+            return Ok(false);
+        };
+
+        let key = format!("{}/{}", upstream_owner, upstream.name).to_lowercase();
+        let heads = self
+            .open_pulls
+            .get_or_try_fetch(key, || async {
+                let heads = self
+                    .client
+                    .open_pull_heads(upstream_owner, &upstream.name)
+                    .await?;
+                Ok::<_, DisforkError>(Arc::new(heads))
+            })
+            .await?;
+
+        Ok(heads.iter().any(|label| {
+            label
+                .split_once(':')
+                .is_some_and(|(head_owner, _)| head_owner.eq_ignore_ascii_case(owner))
+        }))
+    }
+
+    /// Whether the comparison has original work ahead, see [`Comparison::original_ahead`]
+    fn is_ahead(&self, comparison: &Comparison) -> bool {
+        comparison.original_ahead(self.options.ignore_bot_commits) > 0
+    }
+
+    /// Whether the branch's commits landed upstream through a squash or rebase merge,
+    /// going by a merged pull request from the branch whose head is still its tip.
+    /// Always `false` unless squash-merge detection is on.
+    async fn landed_upstream(
+        &self,
+        parent_owner: &str,
+        parent_name: &str,
+        owner: &str,
+        branch: &Branch,
+    ) -> bool {
+        if !self.options.detect_squash_merged {
+            return false;
+        }
+
+        let head = format!("{}:{}", owner, branch.name);
+        self.client
+            .merged_pull_heads(parent_owner, parent_name, &head)
+            .await
+            .is_ok_and(|heads| heads.contains(&branch.commit.sha))
+    }
+
+    /// Whether the fork published releases, or has tags that upstream doesn't have at the
+    /// same commit. Tags copied over when forking don't count.
+    async fn has_own_releases(&self, info: &ForkInfo) -> Result<bool> {
+        let Some(owner) = info.owner_login() else {
+            return Ok(false);
+        };
+        // Releases aren't copied when forking, any release is the fork's own
+        if self.client.has_releases(owner, &info.repo.name).await? {
+            return Ok(true);
+        }
+
+        let tags = self.client.list_tags(owner, &info.repo.name).await?;
+        if tags.is_empty() {
+            return Ok(false);
+        }
+        let Some(upstream) = self.upstream_of(&info.repo) else {
+            return Ok(true);
+        };
+        let Some(upstream_owner) = upstream.owner.as_ref().map(|o| o.login.as_str()) else {
+            return Ok(true);
+        };
+
+        let key = format!("{}/{}", upstream_owner, upstream.name).to_lowercase();
+        let upstream_tags = self
+            .upstream_tags
+            .get_or_try_fetch(key, || async {
+                let tags = self
+                    .client
+                    .list_tags(upstream_owner, &upstream.name)
+                    .await?;
+                Ok::<_, DisforkError>(Arc::new(tags))
+            })
+            .await?;
+
+        Ok(tags
+            .iter()
+            .any(|(name, sha)| upstream_tags.get(name) != Some(sha)))
+    }
+
+    /// The repo a fork is compared to: the network source by default, as intermediate
+    /// forks of forks are often deleted or stale, or the immediate parent if preferred.
+    /// Either link may be missing (e.g. parent deleted), the other one stands in then.
+    fn upstream_of<'a>(&self, repo: &'a Repository) -> Option<&'a Repository> {
+        if self.options.prefer_parent {
+            repo.parent.as_deref().or(repo.source.as_deref())
+        } else {
+            repo.source.as_deref().or(repo.parent.as_deref())
+        }
+    }
+
+    /// Looks the upstream repo up once per run, `None` if it doesn't exist
+    async fn upstream(&self, owner: &str, name: &str) -> Result<Option<Repository>> {
+        let key = format!("{}/{}", owner, name).to_lowercase();
+        let upstream = self
+            .upstreams
+            .get_or_try_fetch(key, || async {
+                match self.client.get_repo(owner, name).await {
+                    Ok(upstream) => Ok(Some(upstream)),
+                    Err(e) if e.is_gone() => Ok(None),
+                    Err(e) => Err(e),
+                }
+            })
+            .await?;
+        Ok(upstream)
+    }
+
+    /// Analyzes forks concurrently, yielding results as they complete.
+    ///
+    /// Dropping the receiver cancels the analyses still in flight.
+    pub fn analyze_all(&self, forks: Vec<Repository>) -> mpsc::Receiver<Result<ForkInfo>> {
+        let (tx, rx) = mpsc::channel(16);
+        let analyzer = self.clone();
+
+        tokio::spawn(async move {
+            let mut tasks = JoinSet::new();
+            for fork in forks {
+                let analyzer = analyzer.clone();
+                tasks.spawn(async move { analyzer.analyze_fork(fork).await });
+            }
+
+            while let Some(result) = tasks.join_next().await {
+                let result = result.unwrap_or_else(|e| Err(e.into()));
+                if tx.send(result).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        rx
+    }
+
     pub async fn analyze_fork(&self, repo: Repository) -> Result<ForkInfo> {
+        let mut info = self.classify_fork(repo).await?;
+        self.apply_keep_rules(&mut info).await?;
+        Ok(info)
+    }
+
+    /// Runs the keep rules on a repo the analysis found useless, see [`KeepRule`]
+    async fn apply_keep_rules(&self, info: &mut ForkInfo) -> Result<()> {
+        if let Some(parent_owner) = info.parent_owner_login() {
+            info.is_self_fork = info
+                .owner_login()
+                .into_iter()
+                .chain(self.options.own_accounts.iter().map(String::as_str))
+                .any(|account| account.eq_ignore_ascii_case(parent_owner));
+        }
+        // Forking your own repo is usually intentional divergence
+        if info.is_self_fork && !self.options.include_self_forks {
+            info.keep(KeepRule::SelfFork);
+        }
+
+        if let Some(source_owner) = &self.options.source_only
+            && info.is_fork()
+        {
+            let source_matches = info
+                .repo
+                .source
+                .as_ref()
+                .and_then(|source| source.owner.as_ref())
+                .is_some_and(|owner| owner.login.eq_ignore_ascii_case(source_owner));
+            if !source_matches {
+                info.keep(KeepRule::SourceOnly);
+            }
+        }
+
+        // Deleting would detach other people's forks from the network
+        if info.repo.forks_count.unwrap_or(0) > 0 && !self.options.include_forked {
+            info.keep(KeepRule::DownstreamForks);
+        }
+
+        // Other repos may have been generated from a template
+        if info.is_template() {
+            info.keep(KeepRule::Template);
+        }
+
+        // A fresh fork is likely the start of a contribution that has no commits yet
+        if let Some(days) = self.options.keep_recent
+            && let Some(pushed) = info.repo.pushed_at
+            && pushed > chrono::Utc::now() - chrono::TimeDelta::days(i64::from(days))
+        {
+            info.keep(KeepRule::RecentPush);
+        }
+
+        // People star forks for the unique patches they carry
+        if let Some(max) = self.options.keep_starred_over
+            && (info.stars() > max || info.watchers() > max)
+        {
+            info.keep(KeepRule::Starred);
+        }
+
+        // Releases are published work, and tags upstream lacks mark the fork's own versions
+        if info.is_useless() && !self.options.ignore_releases {
+            let found = self.has_own_releases(info).await;
+            apply_keep_check(info, KeepRule::Releases, found)?;
+        }
+
+        // Deleting the fork would take its site down, the listing tells which have one
+        if info.is_useless() && info.repo.has_pages == Some(true) {
+            info.keep(KeepRule::Pages);
+        }
+        if info.is_useless()
+            && info.repo.has_pages.is_none()
+            && let Some(owner) = info.owner_login()
+        {
+            let found = self.client.has_pages_site(owner, &info.repo.name).await;
+            apply_keep_check(info, KeepRule::Pages, found)?;
+        }
+
+        // Hooks and deploy keys are set up on purpose, usually for an integration
+        if info.is_useless()
+            && let Some(owner) = info.owner_login()
+        {
+            let found = self.client.has_automation(owner, &info.repo.name).await;
+            apply_keep_check(info, KeepRule::Automation, found)?;
+        }
+
+        // CI experiments and deployments need secrets and environments set up
+        if info.is_useless()
+            && let Some(owner) = info.owner_login()
+        {
+            let found = self.client.has_actions_config(owner, &info.repo.name).await;
+            apply_keep_check(info, KeepRule::ActionsConfig, found)?;
+        }
+
+        // Wikis aren't copied when forking, their pages are the fork's own
+        if info.is_useless()
+            && info.repo.has_wiki != Some(false)
+            && let Some(owner) = info.owner_login()
+        {
+            let found = self.client.has_wiki_pages(owner, &info.repo.name).await;
+            apply_keep_check(info, KeepRule::Wiki, found)?;
+        }
+
+        // Deleting the repo would destroy its development environments
+        if info.is_useless()
+            && let Some(owner) = info.owner_login()
+        {
+            let found = self.client.has_codespaces(owner, &info.repo.name).await;
+            apply_keep_check(info, KeepRule::Codespaces, found)?;
+        }
+
+        // Recent workflow runs mean the fork is used for CI experiments
+        if info.is_useless()
+            && let Some(days) = self.options.ci_activity_days
+            && let Some(owner) = info.owner_login()
+        {
+            let since = chrono::Utc::now() - chrono::TimeDelta::days(i64::from(days));
+            let found = self
+                .client
+                .has_workflow_runs_since(owner, &info.repo.name, since)
+                .await;
+            apply_keep_check(info, KeepRule::CiActivity, found)?;
+        }
+
+        // Tracking issues of its own, the fork is likely an independent project
+        if info.is_useless()
+            && let Some(owner) = info.owner_login()
+        {
+            let found = self.client.has_issue_activity(owner, &info.repo.name).await;
+            apply_keep_check(info, KeepRule::IssueActivity, found)?;
+        }
+
+        // Deleting the fork would close its pull requests
+        if info.is_useless() {
+            let found = self.backs_open_pull(info).await;
+            apply_keep_check(info, KeepRule::OpenPullRequest, found)?;
+        }
+
+        if info.is_useless()
+            && (self.options.only_archived_upstream || self.options.keep_archived_upstream)
+        {
+            let found = self.is_upstream_archived(&info.repo).await.map(|archived| {
+                self.options.only_archived_upstream && !archived
+                    || self.options.keep_archived_upstream && archived
+            });
+            apply_keep_check(info, KeepRule::ArchivedUpstream, found)?;
+        }
+
+        if info.is_useless() && !self.options.exclude_topics.is_empty() {
+            let found = self
+                .topics(&info.repo)
+                .await
+                .map(|topics| has_any_topic(&topics, &self.options.exclude_topics));
+            apply_keep_check(info, KeepRule::Topic, found)?;
+        }
+
+        // Protecting a branch is deliberate setup, not something left over from forking
+        if info.is_useless()
+            && self.options.keep_if_default_branch_protected
+            && let (Some(owner), Some(branch)) =
+                (info.owner_login(), info.repo.default_branch.as_deref())
+        {
+            let found = self
+                .client
+                .is_branch_protected(owner, &info.repo.name, branch)
+                .await;
+            apply_keep_check(info, KeepRule::ProtectedBranch, found)?;
+        }
+
+        // Deployments mean the fork backs something running, even without own commits
+        if info.is_useless()
+            && self.options.keep_with_deployments
+            && let Some(owner) = info.owner_login()
+        {
+            let found = self.client.has_deployments(owner, &info.repo.name).await;
+            apply_keep_check(info, KeepRule::Deployments, found)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compares the fork's `branch` to `base` upstream, reusing the result of an earlier
+    /// comparison of the same head commit if the compare cache is on
+    async fn compare(
+        &self,
+        parent_owner: &str,
+        parent_name: &str,
+        base: &str,
+        owner: &str,
+        branch: &Branch,
+    ) -> Result<Comparison> {
+        // Same commit on both sides, nothing to compare
+        let heads = self.upstream_heads(parent_owner, parent_name).await;
+        if heads.get(base) == Some(&branch.commit.sha) {
+            return Ok(Comparison::new(0, 0, Vec::new()));
+        }
+
+        let head = format!("{}:{}", owner, branch.name);
+        let Some(cache) = &self.compare_cache else {
+            return Ok(self
+                .client
+                .compare_commits(parent_owner, parent_name, base, &head)
+                .await?);
+        };
+
+        // Commits are shared across the fork network, so the fork a head commit is
+        // found in doesn't change the comparison
+        let key = (
+            format!("{}/{}", parent_owner, parent_name),
+            base.to_string(),
+            branch.commit.sha.clone(),
+        );
+        if let Some(comparison) = cache.lock().expect("compare cache poisoned").get(&key) {
+            return Ok(comparison.clone());
+        }
+
+        let comparison = self
+            .client
+            .compare_commits(parent_owner, parent_name, base, &head)
+            .await?;
+        cache
+            .lock()
+            .expect("compare cache poisoned")
+            .insert(key, comparison.clone());
+        Ok(comparison)
+    }
+
+    /// Head SHAs of upstream's branches, listed once per run. Empty if listing fails, as
+    /// it's only a shortcut past comparing.
+    async fn upstream_heads(&self, owner: &str, name: &str) -> Arc<HashMap<String, String>> {
+        let key = format!("{}/{}", owner, name).to_lowercase();
+        self.upstream_heads
+            .get_or_fetch(key, || async {
+                let heads: HashMap<_, _> = match self
+                    .client
+                    .list_branches(owner, name, UPSTREAM_BRANCH_LIMIT)
+                    .await
+                {
+                    Ok(branches) => branches
+                        .items
+                        .into_iter()
+                        .map(|branch| (branch.name, branch.commit.sha))
+                        .collect(),
+                    Err(_) => HashMap::new(),
+                };
+                Arc::new(heads)
+            })
+            .await
+    }
+
+    /// Topics of the repo, fetched if the repo was listed without them
+    pub async fn topics(&self, repo: &Repository) -> Result<Vec<String>> {
+        if let Some(topics) = &repo.topics {
+            return Ok(topics.clone());
+        }
+
         let owner = repo
             .owner
             .as_ref()
             .map(|o| o.login.as_str())
             .ok_or_else(|| anyhow!("Fork repository missing owner information"))?;
-        let repo_name = &repo.name;
-        
-        let repo = self.client.get_repo(owner, repo_name).await?;
-        let branches = self.client.list_branches(owner, repo_name).await?;
+        Ok(self.client.list_topics(owner, &repo.name).await?)
+    }
 
-        if branches.is_empty() {
-            return Ok(ForkInfo {
-                repo,
-                is_useless: true,
-            });
+    /// Whether the fork's upstream is archived, `false` if there's no upstream
+    async fn is_upstream_archived(&self, repo: &Repository) -> Result<bool> {
+        let Some(upstream) = self.upstream_of(repo) else {
+            return Ok(false);
+        };
+        if let Some(archived) = upstream.archived {
+            return Ok(archived);
+        }
+
+        let owner = upstream
+            .owner
+            .as_ref()
+            .map(|o| o.login.as_str())
+            .ok_or_else(|| anyhow!("Parent repository missing owner information"))?;
+        let upstream = self.upstream(owner, &upstream.name).await?;
+        Ok(upstream.and_then(|u| u.archived).unwrap_or(false))
+    }
+
+    /// Fast path for forks that were never touched: the only branch is at the same
+    /// commit as upstream's default branch, so no compare is needed
+    async fn is_pristine(
+        &self,
+        owner: &str,
+        repo_name: &str,
+        branch: &Branch,
+        parent: &Repository,
+    ) -> bool {
+        let (Some(parent_owner), Some(parent_branch)) =
+            (parent.owner.as_ref(), parent.default_branch.as_deref())
+        else {
+            return false;
+        };
+
+        let Ok(parent_head) = self
+            .client
+            .get_branch(&parent_owner.login, &parent.name, parent_branch)
+            .await
+        else {
+            return false;
+        };
+        if parent_head.commit.sha != branch.commit.sha {
+            return false;
+        }
+
+        // Releases always come with tags, which are unique work even if branches aren't
+        matches!(self.client.has_tags(owner, repo_name).await, Ok(false))
+    }
+
+    async fn classify_fork(&self, repo: Repository) -> Result<ForkInfo> {
+        let owner = repo
+            .owner
+            .as_ref()
+            .map(|o| o.login.clone())
+            .ok_or_else(|| anyhow!("Fork repository missing owner information"))?;
+        let repo_name = repo.name.clone();
+
+        // Forks from `fetch_parents` already carry their upstream
+        let repo = if repo.parent.is_some() || repo.source.is_some() {
+            repo
+        } else {
+            self.client.get_repo(&owner, &repo_name).await?
+        };
+        if self.options.fast {
+            return Ok(self.triage(repo));
         }
+        // With a per-fork limit, every branch is listed to pick the most recent ones from
+        let list_limit = match self.options.limit_branches_per_fork {
+            Some(_) => usize::MAX,
+            None => self.options.max_branches,
+        };
+        let branches = self
+            .client
+            .list_branches(&owner, &repo_name, list_limit)
+            .await?;
+        let branch_count = branches.items.len();
 
-        // Skip analyzing repos with too many branches
-        if branches.len() > self.max_branches {
-            return Ok(ForkInfo {
+        let mut info = self
+            .classify_branches(
+                &owner,
+                &repo_name,
                 repo,
-                is_useless: false,
-            });
+                branches.items.clone(),
+                branches.truncated,
+            )
+            .await?;
+        info.branch_count = Some(branch_count);
+        info.branches = Some(branches.items);
+        Ok(info)
+    }
+
+    /// Flags a repository that isn't a fork as useless if it has no branches at all and
+    /// no keep rule applies, only those GitHub reports a size of 0 for are looked up
+    pub async fn analyze_empty(&self, repo: Repository) -> Result<Option<ForkInfo>> {
+        if repo.size != Some(0) {
+            return Ok(None);
         }
+        let Some(owner) = repo.owner.as_ref().map(|owner| owner.login.clone()) else {
+            return Ok(None);
+        };
 
-        let parent = match &repo.parent {
+        let branches = self.client.list_branches(&owner, &repo.name, 1).await?;
+        if !branches.items.is_empty() {
+            return Ok(None);
+        }
+        // Without commits, issues, a wiki or Pages may be what the repo is used for
+        let mut info = ForkInfo::new(repo, true, ForkKind::Empty, None);
+        info.branches = Some(branches.items);
+        self.apply_keep_rules(&mut info).await?;
+        Ok(Some(info))
+    }
+
+    /// Useless if the fork wasn't pushed to since it was created, or since upstream's last
+    /// push. A quick triage, work pushed before upstream's last push is missed.
+    fn triage(&self, repo: Repository) -> ForkInfo {
+        let upstream_pushed = self
+            .upstream_of(&repo)
+            .and_then(|upstream| upstream.pushed_at);
+        let is_useless = repo.pushed_at.is_some_and(|pushed| {
+            repo.created_at.is_some_and(|created| pushed <= created)
+                || upstream_pushed.is_some_and(|upstream_pushed| pushed < upstream_pushed)
+        });
+        ForkInfo::new(repo, is_useless, ForkKind::Triaged, None)
+    }
+
+    async fn classify_branches(
+        &self,
+        owner: &str,
+        repo_name: &str,
+        repo: Repository,
+        branches: Vec<Branch>,
+        truncated: bool,
+    ) -> Result<ForkInfo> {
+        if branches.is_empty() {
+            return Ok(ForkInfo::new(repo, true, ForkKind::Empty, None));
+        }
+
+        let parent = match self.upstream_of(&repo) {
             Some(parent) => parent,
             None => {
-                return Ok(ForkInfo {
-                    repo,
-                    is_useless: true,
-                });
+                // Can't tell whether the branches are unique, leave it for manual review
+                return Ok(ForkInfo::new(repo, false, ForkKind::Orphaned, None));
             }
         };
 
+        if parent.full_name.is_some() && parent.full_name == repo.full_name {
+            return Ok(ForkInfo::new(repo, false, ForkKind::Anomalous, None));
+        }
+
         let parent_owner = parent
             .owner
             .as_ref()
@@ -78,60 +1423,733 @@ impl ForkAnalyzer {
             .ok_or_else(|| anyhow!("Parent repository missing owner information"))?;
         let parent_name = &parent.name;
 
-        // Check if any branch has commits ahead of upstream - compare in parallel
-        let mut tasks = tokio::task::JoinSet::new();
+        if !self.options.require_merged.is_empty() {
+            let mut all_merged = true;
+            for required in &self.options.require_merged {
+                // Nothing to lose if the fork doesn't have the branch at all
+                if !truncated && !branches.iter().any(|b| &b.name == required) {
+                    continue;
+                }
+                let merged = self
+                    .client
+                    .compare_commits(
+                        parent_owner,
+                        parent_name,
+                        required,
+                        &format!("{}:{}", owner, required),
+                    )
+                    .await
+                    .is_ok_and(|c| c.ahead_by == 0);
+                if !merged {
+                    all_merged = false;
+                    break;
+                }
+            }
+            return Ok(ForkInfo::new(repo, all_merged, ForkKind::Regular, None));
+        }
+
+        // Bot branches make otherwise dead forks look ahead, the default branch always counts
+        let listed = branches.len();
+        let branches: Vec<_> = branches
+            .into_iter()
+            .filter(|branch| {
+                repo.default_branch.as_deref() == Some(branch.name.as_str())
+                    || !self
+                        .options
+                        .ignore_branches
+                        .iter()
+                        .any(|pattern| glob_match(pattern, &branch.name))
+            })
+            .collect();
+
+        // Skip analyzing repos with too many branches, unless only some are compared anyway.
+        // A truncated listing is skipped even if few branches are left after ignoring some,
+        // the unlisted ones may be unique.
+        if self.options.limit_branches_per_fork.is_none()
+            && (truncated || branches.len() > self.options.max_branches)
+        {
+            let kind = ForkKind::TooManyBranches {
+                count: if truncated { listed } else { branches.len() },
+                truncated,
+            };
+            return Ok(ForkInfo::new(repo, false, kind, None));
+        }
+
+        // Only compare the most recently committed branches of branch-heavy forks
+        let total_branches = branches.len();
+        let branches = match self.options.limit_branches_per_fork {
+            Some(limit) if total_branches > limit => {
+                let recent = self
+                    .client
+                    .recent_branches(owner, repo_name, limit)
+                    .await
+                    .unwrap_or_default();
+                let default_branch = repo.default_branch.as_deref();
+                branches
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, branch)| {
+                        Some(branch.name.as_str()) == default_branch
+                            || if recent.is_empty() {
+                                *i < limit
+                            } else {
+                                recent.contains(&branch.name)
+                            }
+                    })
+                    .map(|(_, branch)| branch)
+                    .collect()
+            }
+            _ => branches,
+        };
+        let branch_sample = (branches.len() < total_branches).then_some(BranchSample {
+            examined: branches.len(),
+            total: total_branches,
+        });
+
+        let shadows_parent = match (repo.pushed_at, parent.pushed_at) {
+            (Some(pushed), Some(parent_pushed)) => {
+                (pushed - parent_pushed).num_seconds().abs() <= MIRROR_TOLERANCE_SECS
+            }
+            _ => false,
+        };
+
+        if branches.len() == 1
+            && self
+                .is_pristine(owner, repo_name, &branches[0], parent)
+                .await
+        {
+            let divergence = Comparison::new(0, 0, Vec::new());
+            return Ok(ForkInfo::new(
+                repo,
+                true,
+                ForkKind::Pristine,
+                Some(divergence),
+            ));
+        }
+
+        // Compare default branches first, it's also reported as-is
+        let default_branch = repo.default_branch.as_deref();
+        let default = default_branch.and_then(|name| branches.iter().find(|b| b.name == name));
+        let divergence = match default {
+            Some(branch) => match self
+                .compare(
+                    parent_owner,
+                    parent_name,
+                    parent.default_branch.as_deref().unwrap_or(&branch.name),
+                    owner,
+                    branch,
+                )
+                .await
+            {
+                Ok(comparison) => Some(comparison),
+                // The upstream may have been deleted or taken down after the fork's
+                // metadata was cached, the fork may be the only copy left then
+                Err(e)
+                    if e.downcast_ref::<DisforkError>()
+                        .is_some_and(DisforkError::is_gone)
+                        && self.upstream(parent_owner, parent_name).await?.is_none() =>
+                {
+                    return Ok(ForkInfo::new(repo, false, ForkKind::Orphaned, None));
+                }
+                Err(_) => None,
+            },
+            None => None,
+        };
+        let default_landed = match (default, &divergence) {
+            (Some(branch), Some(d)) if d.ahead_by > 0 => {
+                self.landed_upstream(parent_owner, parent_name, owner, branch)
+                    .await
+            }
+            _ => false,
+        };
+
+        // Optionally only other branches count as work, losing default branch commits
+        let mut has_commits_ahead = divergence.as_ref().is_some_and(|d| self.is_ahead(d))
+            && !default_landed
+            && !self.options.ignore_default_branch_only_ahead;
+        let mut total_ahead = divergence.as_ref().map_or(0, |d| d.ahead_by);
+        let compare_all = self.options.total_ahead || self.options.detailed_branches;
+        let mut branch_results = Vec::new();
+        if let (Some(name), Some(d)) = (default_branch, &divergence) {
+            branch_results.push(BranchResult::compared(name, d));
+        }
+
+        // Check if any other branch has commits ahead of upstream - compare in parallel
+        let branch_total = branches.len();
+        let mut tasks = JoinSet::new();
+        let fork_permits = Arc::new(Semaphore::new(
+            self.options
+                .per_fork_compare_limit
+                .unwrap_or(Semaphore::MAX_PERMITS),
+        ));
 
         for branch in branches {
-            let client = self.client.clone();
+            if has_commits_ahead && !compare_all {
+                break;
+            }
+            if divergence.is_some() && Some(branch.name.as_str()) == default_branch {
+                continue;
+            }
+
+            let analyzer = self.clone();
             let parent_owner = parent_owner.to_string();
             let parent_name = parent_name.to_string();
+            let parent_default = parent.default_branch.clone();
             let owner = owner.to_string();
-            let branch_name = branch.name.clone();
+            let fork_permits = fork_permits.clone();
 
             tasks.spawn(async move {
-                // Try to compare branches
-                client
-                    .compare_commits(
-                        &parent_owner,
-                        &parent_name,
-                        &branch_name,
-                        &format!("{}:{}", owner, branch_name),
-                    )
+                let _permit = fork_permits
+                    .acquire()
                     .await
+                    .expect("per-fork semaphore is never closed");
+                // Try to compare branches
+                let mut exists_upstream = true;
+                let mut comparison = analyzer
+                    .compare(&parent_owner, &parent_name, &branch.name, &owner, &branch)
+                    .await;
+                // Upstream has no such branch, e.g. after renaming its default branch,
+                // the branch is still merged if upstream's default branch contains it
+                if let Err(e) = &comparison
+                    && matches!(e.downcast_ref(), Some(DisforkError::NotFound(_)))
+                    && let Some(base) = parent_default
+                {
+                    exists_upstream = false;
+                    comparison = analyzer
+                        .compare(&parent_owner, &parent_name, &base, &owner, &branch)
+                        .await;
+                }
+                let landed = match &comparison {
+                    Ok(c) if c.ahead_by > 0 => {
+                        analyzer
+                            .landed_upstream(&parent_owner, &parent_name, &owner, &branch)
+                            .await
+                    }
+                    _ => false,
+                };
+                (branch.name, comparison, exists_upstream, landed)
             });
         }
 
-        let mut has_commits_ahead = false;
-
         while let Some(result) = tasks.join_next().await {
-            match result? {
-                Ok(ahead_by) => {
-                    if ahead_by > 0 {
+            let (name, comparison, exists_upstream, landed) = result?;
+            match comparison {
+                Ok(comparison) => {
+                    total_ahead += comparison.ahead_by;
+                    if self.is_ahead(&comparison) && !landed {
                         has_commits_ahead = true;
-                        break;
                     }
+                    branch_results.push(BranchResult {
+                        exists_upstream,
+                        ..BranchResult::compared(&name, &comparison)
+                    });
                 }
                 Err(_) => {
-                    // Branch doesn't exist in upstream, consider it as having independent commits
+                    // Can't tell what upstream has, consider it as having independent commits
                     has_commits_ahead = true;
-                    break;
+                    branch_results.push(BranchResult {
+                        name,
+                        ahead: None,
+                        behind: None,
+                        exists_upstream: false,
+                    });
                 }
             }
+            if has_commits_ahead && !compare_all {
+                break;
+            }
         }
 
         // Abort any remaining tasks to avoid unnecessary API calls
         tasks.abort_all();
 
-        if has_commits_ahead {
-            Ok(ForkInfo {
-                repo,
-                is_useless: false,
-            })
+        let (is_useless, kind) = if has_commits_ahead {
+            (false, ForkKind::Regular)
+        } else if shadows_parent {
+            (true, ForkKind::Mirror)
         } else {
-            Ok(ForkInfo {
-                repo,
-                is_useless: true,
-            })
+            (true, ForkKind::Regular)
+        };
+
+        let mut info = ForkInfo::new(repo, is_useless, kind, divergence);
+        info.branch_sample = branch_sample;
+        info.total_ahead = self.options.total_ahead.then_some(total_ahead);
+        branch_results.sort_by(|a, b| a.name.cmp(&b.name));
+        info.branch_results_partial = branch_results.len() < branch_total;
+        info.has_commits_ahead = has_commits_ahead;
+        info.branch_results = Some(branch_results);
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::CompareStatus;
+    use crate::github::fake::{self, FakeGitHub};
+    use crate::report::StatusFilter;
+
+    /// Same as the command line defaults
+    fn options() -> AnalyzerOptions {
+        AnalyzerOptions {
+            max_branches: 20,
+            limit_branches_per_fork: None,
+            total_ahead: false,
+            compare_cache: false,
+            ignore_default_branch_only_ahead: false,
+            detect_squash_merged: false,
+            ignore_bot_commits: false,
+            fast: false,
+            detailed_branches: false,
+            parent_fetch_concurrency: 8,
+            per_fork_compare_limit: None,
+            require_merged: Vec::new(),
+            ignore_branches: ["dependabot/*", "renovate/*", "gh-pages"]
+                .map(String::from)
+                .to_vec(),
+            only_archived_upstream: false,
+            keep_archived_upstream: false,
+            prefer_parent: false,
+            exclude_topics: Vec::new(),
+            keep_if_default_branch_protected: false,
+            keep_recent: None,
+            keep_starred_over: None,
+            ignore_releases: false,
+            ci_activity_days: None,
+            keep_with_deployments: false,
+            own_accounts: Vec::new(),
+            source_only: None,
+            include_forked: false,
+            include_self_forks: false,
         }
     }
+
+    fn upstream() -> Repository {
+        fake::repo("upstream/lib")
+    }
+
+    /// A fork of [`upstream`] with a `main` branch even with upstream's
+    fn github() -> FakeGitHub {
+        FakeGitHub::default()
+            .with_repo(upstream())
+            .with_branches("upstream/lib", vec![fake::branch("main", "base")])
+            .with_repo(fake::fork("me/lib", &upstream()))
+    }
+
+    async fn analyze(github: FakeGitHub, options: AnalyzerOptions) -> ForkInfo {
+        ForkAnalyzer::new(github, options)
+            .analyze_fork(fake::fork("me/lib", &upstream()))
+            .await
+            .expect("analysis succeeds")
+    }
+
+    #[tokio::test]
+    async fn fork_with_merged_branches_is_useless() {
+        let github = github()
+            .with_branches(
+                "me/lib",
+                vec![fake::branch("main", "base"), fake::branch("fix", "fix")],
+            )
+            .with_comparison("upstream/lib", "fix", "me:fix", 0, 2);
+
+        let info = analyze(github, options()).await;
+        assert_eq!(info.verdict, Verdict::Useless);
+        assert_eq!(info.kind, ForkKind::Regular);
+    }
+
+    #[tokio::test]
+    async fn open_pull_check_reuses_listed_branches() {
+        let github = github().with_branches(
+            "me/lib",
+            vec![fake::branch("main", "base"), fake::branch("copy", "base")],
+        );
+
+        let info = analyze(github.clone(), options()).await;
+        assert_eq!(info.verdict, Verdict::Useless);
+        assert_eq!(
+            github.calls("list_branches"),
+            2,
+            "fork and upstream once each"
+        );
+        assert_eq!(
+            github.calls("has_open_pull_from"),
+            1,
+            "once per head commit"
+        );
+    }
+
+    #[tokio::test]
+    async fn status_filter_counts_every_branch() {
+        let github = github()
+            .with_branches(
+                "me/lib",
+                vec![fake::branch("main", "mine"), fake::branch("feature", "new")],
+            )
+            .with_comparison("upstream/lib", "main", "me:main", 0, 0)
+            .with_comparison("upstream/lib", "feature", "me:feature", 2, 0);
+
+        let info = analyze(github, options()).await;
+        assert_eq!(
+            info.divergence.as_ref().map(|d| d.status),
+            Some(CompareStatus::Identical)
+        );
+        assert!(StatusFilter::Ahead.matches(&info));
+        assert!(!StatusFilter::Synced.matches(&info));
+        assert!(!StatusFilter::Behind.matches(&info));
+    }
+
+    #[test]
+    fn failed_lookup_keeps_every_fork() {
+        let fork = || {
+            let fork = fake::fork("me/lib", &upstream());
+            [ForkInfo::new(fork, true, ForkKind::Regular, None)]
+        };
+
+        let mut infos = fork();
+        let lookup = Err(DisforkError::NotFound("Not Found".to_string()));
+        keep_looked_up(&mut infos, lookup, KeepRule::Packages).unwrap();
+        assert_eq!(infos[0].kept_by, Some(KeepRule::CheckFailed));
+
+        let mut infos = fork();
+        let lookup = Err(DisforkError::RateLimited { retry_after: None });
+        assert!(keep_looked_up(&mut infos, lookup, KeepRule::Packages).is_err());
+    }
+
+    #[test]
+    fn duplicates_group_by_parent_ignoring_case() {
+        let old = chrono::Utc::now() - chrono::TimeDelta::days(30);
+        let mut infos =
+            [("me/lib", "upstream/lib"), ("org/lib", "Upstream/Lib")].map(|(name, parent)| {
+                let mut fork = fake::fork(name, &fake::repo(parent));
+                fork.pushed_at = Some(old);
+                ForkInfo::new(fork, true, ForkKind::Regular, None)
+            });
+        infos[1].repo.pushed_at = Some(chrono::Utc::now());
+
+        flag_duplicates(&mut infos);
+        keep_newest_duplicates(&mut infos);
+        assert_eq!(infos[0].duplicate_of.as_deref(), Some("org/lib"));
+        assert!(infos[0].is_useless());
+        assert_eq!(infos[1].kept_by, Some(KeepRule::NewestDuplicate));
+    }
+
+    /// Seconds until the last branch-heavy fork and the last light fork are analyzed, with
+    /// all of them analyzed at once and each compare taking a second of 8 shared permits
+    async fn compare_fan_out(per_fork_compare_limit: Option<usize>) -> (u64, u64) {
+        let mut github = github().with_compare_latency(std::time::Duration::from_secs(1), 8);
+        let forks = ["heavy1", "heavy2", "light1", "light2", "light3", "light4"];
+        for owner in forks {
+            let count = if owner.starts_with("heavy") { 30 } else { 2 };
+            let branches: Vec<_> = (0..count)
+                .map(|i| match i {
+                    0 => "main".to_string(),
+                    i => format!("b{}", i),
+                })
+                .collect();
+            github = github.with_branches(
+                &format!("{}/lib", owner),
+                branches
+                    .iter()
+                    .map(|name| fake::branch(name, &format!("{}-{}", owner, name)))
+                    .collect(),
+            );
+            for name in &branches {
+                let head = format!("{}:{}", owner, name);
+                github = github.with_comparison("upstream/lib", name, &head, 0, 1);
+            }
+        }
+        let analyzer = ForkAnalyzer::new(
+            github,
+            AnalyzerOptions {
+                max_branches: 100,
+                per_fork_compare_limit,
+                ..options()
+            },
+        );
+
+        let start = tokio::time::Instant::now();
+        let mut tasks = JoinSet::new();
+        for owner in forks {
+            let analyzer = analyzer.clone();
+            let fork = fake::fork(&format!("{}/lib", owner), &upstream());
+            tasks.spawn(async move {
+                analyzer
+                    .analyze_fork(fork)
+                    .await
+                    .expect("analysis succeeds");
+                (owner, start.elapsed().as_secs())
+            });
+        }
+        let (mut heavy, mut light) = (0, 0);
+        while let Some(result) = tasks.join_next().await {
+            let (owner, secs) = result.expect("analysis task");
+            let last = if owner.starts_with("heavy") {
+                &mut heavy
+            } else {
+                &mut light
+            };
+            *last = (*last).max(secs);
+        }
+        (heavy, light)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn per_fork_compare_limit_lets_light_forks_through() {
+        // Uncapped, the heavy forks queue all their compares first and hold up the others.
+        // Capped, the last heavy forks can't use every permit, so they finish later.
+        assert_eq!(compare_fan_out(None).await, (9, 9));
+        assert_eq!(compare_fan_out(Some(3)).await, (11, 3));
+    }
+
+    #[tokio::test]
+    async fn mirror_is_useless() {
+        let pushed = chrono::Utc::now();
+        let mut upstream = upstream();
+        upstream.pushed_at = Some(pushed);
+        let mut fork = fake::fork("me/lib", &upstream);
+        fork.pushed_at = Some(pushed);
+        let github = github()
+            .with_repo(upstream)
+            .with_branches(
+                "me/lib",
+                vec![fake::branch("main", "base"), fake::branch("fix", "fix")],
+            )
+            .with_comparison("upstream/lib", "fix", "me:fix", 0, 2);
+
+        let info = ForkAnalyzer::new(github, options())
+            .analyze_fork(fork)
+            .await
+            .expect("analysis succeeds");
+        assert_eq!(info.kind, ForkKind::Mirror);
+        assert!(info.is_useless());
+    }
+
+    #[test]
+    fn kept_mirror_has_no_unique_work() {
+        let info = ForkInfo::new(fake::repo("me/lib"), false, ForkKind::Mirror, None);
+        assert_eq!(info.verdict, Verdict::Protected);
+    }
+
+    #[tokio::test]
+    async fn fork_without_parent_or_source_is_orphaned() {
+        let mut orphan = fake::repo("me/lib");
+        orphan.fork = Some(true);
+        let github = FakeGitHub::default()
+            .with_repo(orphan.clone())
+            .with_branches(
+                "me/lib",
+                vec![fake::branch("main", "base"), fake::branch("fix", "fix")],
+            );
+
+        let info = ForkAnalyzer::new(github, options())
+            .analyze_fork(orphan)
+            .await
+            .expect("analysis succeeds");
+        assert_eq!(info.kind, ForkKind::Orphaned);
+        assert_eq!(info.verdict, Verdict::Orphaned);
+    }
+
+    #[tokio::test]
+    async fn fork_without_parent_is_compared_to_source() {
+        let mut fork = fake::fork("me/lib", &upstream());
+        fork.parent = None;
+        let github = github()
+            .with_branches(
+                "me/lib",
+                vec![fake::branch("main", "base"), fake::branch("fix", "fix")],
+            )
+            .with_comparison("upstream/lib", "fix", "me:fix", 0, 2);
+
+        let info = ForkAnalyzer::new(github, options())
+            .analyze_fork(fork)
+            .await
+            .expect("analysis succeeds");
+        assert_eq!(info.kind, ForkKind::Regular);
+        assert_eq!(info.verdict, Verdict::Useless);
+    }
+
+    #[tokio::test]
+    async fn fork_with_too_many_branches_is_skipped() {
+        let branches = (0..25)
+            .map(|i| fake::branch(&format!("branch-{}", i), "base"))
+            .collect();
+        let github = github().with_branches("me/lib", branches);
+
+        let info = analyze(github, options()).await;
+        assert_eq!(
+            info.kind,
+            ForkKind::TooManyBranches {
+                count: 25,
+                truncated: false
+            }
+        );
+        assert_eq!(info.verdict, Verdict::Unknown);
+    }
+
+    #[tokio::test]
+    async fn branch_limit_samples_instead_of_skipping() {
+        let branches = (0..150)
+            .map(|i| match i {
+                0 => fake::branch("main", "base"),
+                i => fake::branch(&format!("branch-{}", i), "base"),
+            })
+            .collect();
+        let github = github().with_branches("me/lib", branches);
+        let options = AnalyzerOptions {
+            limit_branches_per_fork: Some(5),
+            ..options()
+        };
+
+        let info = analyze(github, options).await;
+        assert_eq!(info.kind, ForkKind::Regular);
+        assert_eq!(info.branch_count, Some(150));
+        assert!(info.branch_sample.is_some_and(|sample| sample.total == 150));
+    }
+
+    #[tokio::test]
+    async fn truncated_listing_is_skipped_even_if_mostly_ignored() {
+        // The first page is nearly all bot branches, unique work may be on the next one
+        let branches = (0..150)
+            .map(|i| match i {
+                0 => fake::branch("main", "base"),
+                i if i < 100 => fake::branch(&format!("dependabot/{}", i), "base"),
+                i => fake::branch(&format!("feature-{}", i), "unique"),
+            })
+            .collect();
+        let github = github().with_branches("me/lib", branches);
+
+        let info = analyze(github, options()).await;
+        assert!(matches!(
+            info.kind,
+            ForkKind::TooManyBranches {
+                truncated: true,
+                ..
+            }
+        ));
+        assert!(!info.is_useless());
+    }
+
+    #[tokio::test]
+    async fn failed_compare_counts_as_ahead() {
+        let github = github()
+            .with_branches(
+                "me/lib",
+                vec![fake::branch("main", "base"), fake::branch("fix", "fix")],
+            )
+            .with_compare_error("upstream/lib", "fix", "me:fix", 502);
+
+        let info = analyze(github, options()).await;
+        assert_eq!(info.kind, ForkKind::Regular);
+        assert!(!info.is_useless());
+    }
+
+    #[tokio::test]
+    async fn listed_pages_site_keeps_fork_without_lookup() {
+        let github = github()
+            .with_branches("me/lib", vec![fake::branch("main", "base")])
+            .with_check_error("has_pages_site", 403);
+        let mut fork = fake::fork("me/lib", &upstream());
+        fork.has_pages = Some(true);
+
+        let info = ForkAnalyzer::new(github.clone(), options())
+            .analyze_fork(fork)
+            .await
+            .expect("analysis succeeds");
+        assert_eq!(info.kept_by, Some(KeepRule::Pages));
+        assert_eq!(github.calls("has_pages_site"), 0);
+    }
+
+    #[tokio::test]
+    async fn failed_keep_check_keeps_fork() {
+        let github = github()
+            .with_branches("me/lib", vec![fake::branch("main", "base")])
+            .with_check_error("has_pages_site", 500);
+
+        let info = analyze(github, options()).await;
+        assert_eq!(info.verdict, Verdict::Protected);
+        assert_eq!(info.kept_by, Some(KeepRule::CheckFailed));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_keep_check_aborts() {
+        let github = github()
+            .with_branches("me/lib", vec![fake::branch("main", "base")])
+            .with_check_error("has_issue_activity", 429);
+
+        let result = ForkAnalyzer::new(github, options())
+            .analyze_fork(fake::fork("me/lib", &upstream()))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn empty_repo_goes_through_keep_rules() {
+        let mut repo = fake::repo("me/tracker");
+        repo.size = Some(0);
+        let analyzer = ForkAnalyzer::new(FakeGitHub::default(), options());
+        let info = analyzer.analyze_empty(repo.clone()).await.unwrap();
+        assert!(info.is_some_and(|info| info.is_useless() && !info.is_fork()));
+
+        let github = FakeGitHub::default().with_check("has_issue_activity");
+        let info = ForkAnalyzer::new(github, options())
+            .analyze_empty(repo)
+            .await
+            .unwrap()
+            .expect("repo is empty");
+        assert_eq!(info.kept_by, Some(KeepRule::IssueActivity));
+    }
+
+    #[tokio::test]
+    async fn comparing_stops_at_first_branch_ahead_unless_detailed() {
+        let branches = ["main", "a", "b", "c"].map(|name| fake::branch(name, name));
+        let github = github()
+            .with_branches("me/lib", branches.to_vec())
+            .with_comparison("upstream/lib", "main", "me:main", 0, 0)
+            .with_comparison("upstream/lib", "a", "me:a", 1, 0)
+            .with_comparison("upstream/lib", "b", "me:b", 1, 0)
+            .with_comparison("upstream/lib", "c", "me:c", 1, 0);
+
+        let info = analyze(github.clone(), options()).await;
+        assert!(info.branch_results_partial);
+        assert_eq!(info.branches_ahead(), Some((1, 1)));
+
+        let detailed = AnalyzerOptions {
+            detailed_branches: true,
+            ..options()
+        };
+        let info = analyze(github, detailed).await;
+        assert!(!info.branch_results_partial);
+        assert_eq!(info.branches_ahead(), Some((3, 1)));
+    }
+
+    #[tokio::test]
+    async fn compare_against_deleted_upstream_is_orphaned() {
+        // Upstream is gone by the time of comparing, only the fork's metadata has it
+        let github = FakeGitHub::default()
+            .with_branches(
+                "me/lib",
+                vec![fake::branch("main", "mine"), fake::branch("fix", "fix")],
+            )
+            .with_compare_error("upstream/lib", "main", "me:main", 404);
+
+        let info = analyze(github, options()).await;
+        assert_eq!(info.kind, ForkKind::Orphaned);
+        assert_eq!(info.verdict, Verdict::Orphaned);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_lookups_fetch_once() {
+        let cache = Coalesced::<usize>::default();
+        let fetches = std::sync::atomic::AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            Ok::<_, DisforkError>(42)
+        };
+
+        let lookup = || cache.get_or_try_fetch("upstream/lib".to_string(), fetch);
+        let (a, b, c) = tokio::join!(lookup(), lookup(), lookup());
+        assert_eq!((a.unwrap(), b.unwrap(), c.unwrap()), (42, 42, 42));
+        assert_eq!(fetches.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
 }
@@ -1,14 +1,61 @@
-use crate::analyzer::ForkInfo;
+use crate::analyzer::{ForkInfo, ForkKind};
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use console::{Term, style};
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::{Confirm, MultiSelect};
 use indicatif::{ProgressBar, ProgressStyle};
+use std::io::Write;
+use std::process::{Command, Stdio};
 use std::time::Duration;
 
+/// Which repositories are checked when the selection prompt opens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preselect {
+    Useless,
+    None,
+    All,
+}
+
+/// Order of the repositories listed before deletion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SummarySort {
+    /// Alphabetical by full name
+    Name,
+    /// Most stars, downstream forks, size, and recent pushes first
+    Risk,
+    /// Most commits ahead of upstream first, over all branches with --total-ahead
+    Ahead,
+}
+
+/// Behavior when deleting a repository fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OnError {
+    /// Carry on with the remaining repositories
+    Continue,
+    /// Stop and list the repositories left untouched
+    Abort,
+    /// Ask whether to carry on
+    Prompt,
+}
+
+/// Answer of the deletion confirmation when just pressing Enter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PromptDefault {
+    /// Cancel the deletion
+    Keep,
+    /// Proceed with the deletion
+    Delete,
+}
+
 pub struct CliInterface {
     term: Term,
     theme: ColorfulTheme,
+    spinner_tick: Option<Duration>,
+    show_branch_counts: bool,
+    show_upstreams: bool,
+    show_urls: bool,
+    preselect_mirrors: bool,
 }
 
 impl CliInterface {
@@ -16,9 +63,44 @@ impl CliInterface {
         Self {
             term: Term::stderr(),
             theme: ColorfulTheme::default(),
+            spinner_tick: Some(Duration::from_millis(100)),
+            show_branch_counts: false,
+            show_upstreams: false,
+            show_urls: false,
+            preselect_mirrors: true,
         }
     }
 
+    /// Sets how often spinners animate, `None` shows a static message instead
+    pub fn with_spinner_tick(mut self, tick: Option<Duration>) -> Self {
+        self.spinner_tick = tick;
+        self
+    }
+
+    /// Shows how many branches each fork has in the selection list
+    pub fn with_branch_counts(mut self, show: bool) -> Self {
+        self.show_branch_counts = show;
+        self
+    }
+
+    /// Shows the parent of each fork in the selection list
+    pub fn with_upstreams(mut self, show: bool) -> Self {
+        self.show_upstreams = show;
+        self
+    }
+
+    /// Shows links to each fork and its parent in the selection and deletion lists
+    pub fn with_urls(mut self, show: bool) -> Self {
+        self.show_urls = show;
+        self
+    }
+
+    /// Whether useless mirror forks are selected by default along with other useless forks
+    pub fn with_mirrors_preselected(mut self, preselect: bool) -> Self {
+        self.preselect_mirrors = preselect;
+        self
+    }
+
     pub fn show_welcome(&self) -> Result<()> {
         self.term.write_line(&format!(
             "\n{} {}\n",
@@ -60,50 +142,237 @@ impl CliInterface {
 
     pub fn create_spinner(&self, message: &str) -> Result<ProgressBar> {
         let pb = ProgressBar::new_spinner();
+        // Without ticking, the spinner is only redrawn on updates
+        let template = match self.spinner_tick {
+            Some(_) => "{spinner:.cyan} {msg}",
+            None => "{msg}",
+        };
         let style = ProgressStyle::default_spinner()
-            .template("{spinner:.cyan} {msg}")
+            .template(template)
             .context("invalid spinner template")?;
         pb.set_style(style);
         pb.set_message(message.to_string());
-        pb.enable_steady_tick(Duration::from_millis(100));
+        if let Some(tick) = self.spinner_tick {
+            pb.enable_steady_tick(tick);
+        }
         Ok(pb)
     }
 
-    pub fn select_repos_to_delete(&self, fork_infos: &[ForkInfo]) -> Result<Vec<usize>> {
+    pub fn select_repos_to_delete(
+        &self,
+        fork_infos: &[ForkInfo],
+        preselect: Preselect,
+        preselected: Option<&[usize]>,
+    ) -> Result<Vec<usize>> {
         if fork_infos.is_empty() {
             self.term
                 .write_line(&style("✓ No fork repositories found!").green().to_string())?;
             return Ok(vec![]);
         }
 
+        let fork_count = fork_infos.iter().filter(|f| f.is_fork()).count();
+        let empty_count = fork_infos.len() - fork_count;
         self.term.write_line("")?;
         self.term.write_line(&format!(
-            "{} Found {} fork repositories",
+            "{} Found {} fork repositories{}",
             style("ℹ").bold().cyan(),
-            fork_infos.len()
+            fork_count,
+            if empty_count > 0 {
+                format!(" and {} empty repositories", empty_count)
+            } else {
+                String::new()
+            }
         ))?;
 
-        let useless_count = fork_infos.iter().filter(|f| f.is_useless).count();
+        let useless_count = fork_infos.iter().filter(|f| f.is_useless()).count();
         self.term.write_line(&format!(
-            "{} {} are useless, selected by default",
+            "{} {} are useless{}",
             style("→").cyan(),
-            style(useless_count).yellow()
+            style(useless_count).yellow(),
+            if preselect == Preselect::Useless {
+                ", selected by default"
+            } else {
+                ""
+            }
         ))?;
+
+        let mirror_count = fork_infos
+            .iter()
+            .filter(|f| f.kind == ForkKind::Mirror)
+            .count();
+        if mirror_count > 0 {
+            self.term.write_line(&format!(
+                "{} {} are mirrors kept in sync with upstream{}",
+                style("→").cyan(),
+                style(mirror_count).yellow(),
+                if preselect == Preselect::Useless && !self.preselect_mirrors {
+                    ", not selected without --delete-mirrors"
+                } else {
+                    ""
+                }
+            ))?;
+        }
+
+        let pristine_count = fork_infos
+            .iter()
+            .filter(|f| f.kind == ForkKind::Pristine)
+            .count();
+        if pristine_count > 0 {
+            self.term.write_line(&format!(
+                "{} {} are pristine forks never changed since forking",
+                style("→").cyan(),
+                style(pristine_count).yellow()
+            ))?;
+        }
         self.term.write_line("")?;
 
         let items: Vec<String> = fork_infos
             .iter()
             .map(|info| {
-                let repo_name = info.full_name();
-                if info.is_useless {
-                    format!("{} - {}", repo_name, style("useless").red())
+                let mut repo_name = format!("{} {}", info.full_name(), divergence_badge(info));
+                // Skipped forks already show their branch count
+                if self.show_branch_counts
+                    && !matches!(info.kind, ForkKind::TooManyBranches { .. })
+                    && let Some(count) = info.branch_count
+                {
+                    let ahead = info
+                        .divergence
+                        .as_ref()
+                        .map(|d| format!(", {} ahead", d.ahead_by))
+                        .unwrap_or_default();
+                    repo_name.push_str(&format!(
+                        " {}",
+                        style(format!("({} branches{})", count, ahead)).dim()
+                    ));
+                }
+                if let Some((count, max)) = info.branches_ahead() {
+                    let branches = if count == 1 { "branch" } else { "branches" };
+                    // Comparing stopped at the first branch ahead, there may be more
+                    let summary = if info.branch_results_partial {
+                        format!(
+                            "({}+ {}, max {}+ ahead, not all compared)",
+                            count, branches, max
+                        )
+                    } else {
+                        format!("({} {}, max {} ahead)", count, branches, max)
+                    };
+                    repo_name.push_str(&format!(" {}", style(summary).dim()));
+                }
+                if let Some(sample) = info.branch_sample {
+                    repo_name.push_str(&format!(
+                        " {}",
+                        style(format!(
+                            "(examined {} of {} branches)",
+                            sample.examined, sample.total
+                        ))
+                        .dim()
+                    ));
+                }
+                if info.open_issues() > 0 {
+                    repo_name.push_str(&format!(
+                        " {}",
+                        style(format!("({} open issues and PRs)", info.open_issues())).dim()
+                    ));
+                }
+                if info.stars() > 0 || info.watchers() > 0 {
+                    repo_name.push_str(&format!(
+                        " {}",
+                        style(format!(
+                            "(★ {}, {} watching)",
+                            info.stars(),
+                            info.watchers()
+                        ))
+                        .dim()
+                    ));
+                }
+                if self.show_upstreams
+                    && let Some(upstream) = info.parent_full_name()
+                {
+                    repo_name.push_str(&format!(" {}", style(format!("← {}", upstream)).dim()));
+                }
+                if self.show_urls {
+                    repo_name.push_str(&format!(" {}", style(urls(info)).dim()));
+                }
+                if let Some(newest) = &info.duplicate_of {
+                    repo_name.push_str(&format!(
+                        " {}",
+                        style(format!("(duplicate, consider keeping {})", newest)).yellow()
+                    ));
+                }
+                if info.is_archived() {
+                    repo_name.push_str(&format!(" {}", style("[archived]").bold().dim()));
+                }
+                if info.is_template() {
+                    repo_name.push_str(&format!(" {}", style("[template]").bold().cyan()));
+                }
+                if info.is_self_fork {
+                    repo_name.push_str(&format!(
+                        " {}",
+                        style("(fork of your own repo)").bold().yellow()
+                    ));
+                }
+                if let Some(network) = &info.network {
+                    repo_name.push_str(&format!(
+                        " {}",
+                        style(format!("[network: {}]", network)).cyan()
+                    ));
+                }
+                if info.kind == ForkKind::Mirror {
+                    format!("{} - {}", repo_name, style("mirror").magenta())
+                } else if let ForkKind::TooManyBranches { count, truncated } = info.kind {
+                    let plus = if truncated { "+" } else { "" };
+                    format!(
+                        "{} - {}",
+                        repo_name,
+                        style(format!("{}{} branches, skipped", count, plus)).yellow()
+                    )
+                } else if let Some(rule) = info.kept_by {
+                    format!(
+                        "{} - {}",
+                        repo_name,
+                        style(format!("kept, {}", rule.describe())).green()
+                    )
+                } else if info.kind == ForkKind::Pristine {
+                    format!("{} - {}", repo_name, style("useless, pristine").red())
+                } else if info.is_useless() {
+                    let reasons = info.reasons().join("; ");
+                    format!(
+                        "{} - {}",
+                        repo_name,
+                        style(format!("useless, {}", reasons)).red()
+                    )
+                } else if info.kind == ForkKind::Anomalous {
+                    format!(
+                        "{} - {}",
+                        repo_name,
+                        style("anomalous metadata, needs review").yellow()
+                    )
+                } else if info.kind == ForkKind::Orphaned {
+                    format!(
+                        "{} - {}",
+                        repo_name,
+                        style("orphaned, upstream gone, may be the only copy").blue()
+                    )
                 } else {
-                    repo_name.to_string()
+                    let verdict =
+                        format!("{}, {}", info.verdict.label(), info.reasons().join("; "));
+                    format!("{} - {}", repo_name, style(verdict).dim())
                 }
             })
             .collect();
 
-        let defaults: Vec<bool> = fork_infos.iter().map(|f| f.is_useless).collect();
+        let defaults: Vec<bool> = fork_infos
+            .iter()
+            .enumerate()
+            .map(|(i, f)| match (preselected, preselect) {
+                (Some(preselected), _) => preselected.contains(&i),
+                (None, Preselect::Useless) => {
+                    f.is_useless() && (self.preselect_mirrors || f.kind != ForkKind::Mirror)
+                }
+                (None, Preselect::None) => false,
+                (None, Preselect::All) => true,
+            })
+            .collect();
 
         let selections = MultiSelect::with_theme(&self.theme)
             .with_prompt("Select repositories to delete (Space to toggle, Enter to confirm)")
@@ -114,7 +383,40 @@ impl CliInterface {
         Ok(selections)
     }
 
-    pub async fn show_cooldown(&self, seconds: u64, is_batch: bool) -> Result<()> {
+    /// `branches` are `(repository, branch)` pairs, none selected by default
+    pub fn select_branches_to_delete(&self, branches: &[(&str, &str)]) -> Result<Vec<usize>> {
+        let items: Vec<String> = branches
+            .iter()
+            .map(|(repo, branch)| format!("{} {}", repo, style(branch).cyan()))
+            .collect();
+
+        let selections = MultiSelect::with_theme(&self.theme)
+            .with_prompt("Select branches to delete (Space to toggle, Enter to confirm)")
+            .items(&items)
+            .interact()?;
+
+        Ok(selections)
+    }
+
+    pub fn confirm_branch_deletion(&self, count: usize, default: PromptDefault) -> Result<bool> {
+        let confirmed = Confirm::with_theme(&self.theme)
+            .with_prompt(format!(
+                "Are you sure you want to delete {} branches?",
+                count
+            ))
+            .default(default == PromptDefault::Delete)
+            .interact()?;
+
+        Ok(confirmed)
+    }
+
+    /// Counts down before deleting, `false` if `stop` resolved first
+    pub async fn show_cooldown(
+        &self,
+        seconds: u64,
+        is_batch: bool,
+        stop: impl Future<Output = ()>,
+    ) -> Result<bool> {
         let action = if is_batch {
             "batch deletion"
         } else {
@@ -136,26 +438,95 @@ impl CliInterface {
         pb.set_style(style);
         pb.set_message("Cooling down".to_string());
 
-        for _ in 0..seconds {
-            tokio::time::sleep(Duration::from_secs(1)).await;
-            pb.inc(1);
+        let countdown = async {
+            for _ in 0..seconds {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                pb.inc(1);
+            }
+        };
+        tokio::select! {
+            _ = countdown => {}
+            _ = stop => {
+                pb.abandon_with_message("Stopped");
+                return Ok(false);
+            }
         }
 
         pb.finish_with_message("Ready!");
-        Ok(())
+        Ok(true)
     }
 
-    pub fn confirm_deletion(&self, count: usize, is_batch: bool) -> Result<bool> {
+    pub fn confirm_deletion(
+        &self,
+        selected: &[&ForkInfo],
+        is_batch: bool,
+        default: PromptDefault,
+    ) -> Result<bool> {
         self.term.write_line("")?;
+        self.term.write_line(&format!(
+            "{} {}",
+            style("ℹ").cyan(),
+            deletion_digest(selected)
+        ))?;
 
         let message = if is_batch {
-            format!("Are you sure you want to delete {} repositories?", count)
+            format!(
+                "Are you sure you want to delete {} repositories?",
+                selected.len()
+            )
         } else {
             "Are you sure you want to delete this repository?".to_string()
         };
 
         let confirmed = Confirm::with_theme(&self.theme)
             .with_prompt(message)
+            .default(default == PromptDefault::Delete)
+            .interact()?;
+
+        Ok(confirmed)
+    }
+
+    /// Lists the repositories about to be deleted on stderr, keeping stdout for machine
+    /// output. `pager` forces paging
+    /// through `$PAGER` on or off, by default only lists taller than the terminal are paged
+    pub fn show_deletion_list(&self, selected: &[&ForkInfo], pager: Option<bool>) -> Result<()> {
+        let lines: Vec<String> = selected
+            .iter()
+            .map(|info| {
+                if self.show_urls {
+                    format!("  - {} {}", info.full_name(), urls(info))
+                } else {
+                    format!("  - {}", info.full_name())
+                }
+            })
+            .collect();
+
+        let use_pager = pager.unwrap_or_else(|| {
+            self.term.is_term()
+                && self
+                    .term
+                    .size_checked()
+                    .is_some_and(|(rows, _)| lines.len() >= rows as usize)
+        });
+        if use_pager && page(&lines).is_ok() {
+            return Ok(());
+        }
+
+        for line in &lines {
+            self.term.write_line(line)?;
+        }
+        Ok(())
+    }
+
+    /// One `  - item` line of a list introduced by another message
+    pub fn show_list_item(&self, item: &str) -> Result<()> {
+        self.term.write_line(&format!("  - {}", item))?;
+        Ok(())
+    }
+
+    pub fn confirm_continue(&self) -> Result<bool> {
+        let confirmed = Confirm::with_theme(&self.theme)
+            .with_prompt("Continue deleting the remaining repositories?")
             .default(false)
             .interact()?;
 
@@ -180,3 +551,122 @@ impl CliInterface {
         Ok(())
     }
 }
+
+/// Pipes `lines` through `$PAGER` (`less` by default) to stderr and waits for it to exit
+fn page(lines: &[String]) -> Result<()> {
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+    let mut parts = pager.split_whitespace();
+    let program = parts.next().context("PAGER is empty")?;
+
+    let mut child = Command::new(program)
+        .args(parts)
+        .stdin(Stdio::piped())
+        .stdout(std::io::stderr())
+        .spawn()
+        .with_context(|| format!("Failed to start pager {}", program))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The pager may be quit before reading everything
+        for line in lines {
+            if writeln!(stdin, "{}", line).is_err() {
+                break;
+            }
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Links to the fork and its parent, e.g. `<fork url> ← <parent url>`
+fn urls(info: &ForkInfo) -> String {
+    match (info.html_url(), info.parent_html_url()) {
+        (Some(url), Some(parent)) => format!("{} ← {}", url, parent),
+        (Some(url), None) => url.to_string(),
+        (None, Some(parent)) => format!("← {}", parent),
+        (None, None) => String::new(),
+    }
+}
+
+/// Compact ahead/behind summary of the default branch, e.g. `⇡2 ⇣40`, followed by
+/// the total ahead over all branches if known, e.g. `⇡2 ⇣40 Σ⇡7`
+fn divergence_badge(info: &ForkInfo) -> String {
+    let badge = match &info.divergence {
+        Some(d) => format!("⇡{} ⇣{}", d.ahead_by, d.behind_by),
+        None => "?".to_string(),
+    };
+    match info.total_ahead {
+        Some(total) => style(format!("{} Σ⇡{}", badge, total)).dim().to_string(),
+        None => style(badge).dim().to_string(),
+    }
+}
+
+/// One-line summary of what's about to be deleted, e.g.
+/// `Deleting 23 forks: 18 empty, 5 synced; total 1.2 GiB; 3 have downstream forks`, or
+/// `Deleting 25 repositories (23 forks): ...` with empty repos that aren't forks
+fn deletion_digest(selected: &[&ForkInfo]) -> String {
+    let mut categories: Vec<(&str, usize)> = Vec::new();
+    for info in selected {
+        let category = match info.kind {
+            ForkKind::Empty if !info.is_fork() => "empty non-forks",
+            ForkKind::Empty => "empty",
+            ForkKind::Pristine => "pristine",
+            ForkKind::Mirror => "mirrors",
+            ForkKind::Orphaned => "orphaned",
+            ForkKind::Anomalous => "anomalous",
+            ForkKind::Triaged if info.is_useless() => "unpushed",
+            ForkKind::Triaged => "pushed to",
+            ForkKind::TooManyBranches { .. } => "unanalyzed",
+            ForkKind::Regular if info.is_useless() => "synced",
+            ForkKind::Regular => "with unique work",
+        };
+        match categories.iter_mut().find(|(c, _)| *c == category) {
+            Some((_, count)) => *count += 1,
+            None => categories.push((category, 1)),
+        }
+    }
+
+    let categories: Vec<String> = categories
+        .iter()
+        .map(|(category, count)| format!("{} {}", count, category))
+        .collect();
+    let size_kib: u64 = selected
+        .iter()
+        .map(|info| info.repo.size.unwrap_or(0) as u64)
+        .sum();
+    let fork_count = selected.iter().filter(|info| info.is_fork()).count();
+    let subject = if fork_count == selected.len() {
+        format!("{} forks", fork_count)
+    } else {
+        format!("{} repositories ({} forks)", selected.len(), fork_count)
+    };
+    let mut digest = format!(
+        "Deleting {}: {}; total {}",
+        subject,
+        categories.join(", "),
+        format_size(size_kib)
+    );
+
+    let downstream = selected
+        .iter()
+        .filter(|info| info.repo.forks_count.unwrap_or(0) > 0)
+        .count();
+    if downstream > 0 {
+        digest.push_str(&format!("; {} have downstream forks", downstream));
+    }
+    digest
+}
+
+/// Human-readable size from GitHub's repository size, which is in KiB
+fn format_size(kib: u64) -> String {
+    const UNITS: [&str; 3] = ["MiB", "GiB", "TiB"];
+
+    if kib < 1024 {
+        return format!("{} KiB", kib);
+    }
+    let mut size = kib as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
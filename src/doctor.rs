@@ -0,0 +1,82 @@
+use crate::cli::CliInterface;
+use crate::github::GitHubClient;
+use anyhow::Result;
+
+/// Checks the setup step by step and prints a pass/fail checklist, without modifying anything
+pub async fn run(cli: &CliInterface, token: Option<String>, account: Option<&str>) -> Result<()> {
+    let mut failures = 0;
+    let mut check = |passed: bool, message: String| -> Result<()> {
+        if passed {
+            cli.show_success(&message)
+        } else {
+            failures += 1;
+            cli.show_error(&message)
+        }
+    };
+
+    match GitHubClient::check_connectivity().await {
+        Ok(()) => check(true, "GitHub API is reachable".to_string())?,
+        Err(e) => check(false, format!("GitHub API is unreachable: {}", e))?,
+    }
+
+    let Some(token) = token else {
+        check(
+            false,
+            "No token found, set GITHUB_TOKEN or pass --github-token".to_string(),
+        )?;
+        anyhow::bail!("{} check(s) failed", failures);
+    };
+    let client = GitHubClient::new(token, 1, 0)?;
+
+    let user = match client.current_user().await {
+        Ok(user) => {
+            check(true, format!("Token is valid, authenticated as {}", user))?;
+            user
+        }
+        Err(e) => {
+            check(false, format!("Token is invalid: {}", e))?;
+            anyhow::bail!("{} check(s) failed", failures);
+        }
+    };
+
+    match client.token_scopes().await {
+        Ok(Some(scopes)) if scopes.split(',').any(|s| s.trim() == "delete_repo") => {
+            check(true, format!("Token scopes: {}", scopes))?
+        }
+        Ok(Some(scopes)) => check(false, format!("Token scopes lack delete_repo: {}", scopes))?,
+        Ok(None) => check(
+            true,
+            "Token has no OAuth scopes (fine-grained or GitHub App token)".to_string(),
+        )?,
+        Err(e) => check(false, format!("Failed to read token scopes: {}", e))?,
+    }
+
+    match client.rate_limit().await {
+        Ok(rate_limit) => {
+            let core = rate_limit.resources.core;
+            check(
+                core.remaining > 0,
+                format!(
+                    "Rate limit: {}/{} requests left",
+                    core.remaining, core.limit
+                ),
+            )?
+        }
+        Err(e) => check(false, format!("Failed to read rate limit: {}", e))?,
+    }
+
+    let account = account.unwrap_or(&user);
+    match client.account_type(account).await {
+        Ok(account_type) => check(
+            true,
+            format!("Account {} is accessible ({})", account, account_type),
+        )?,
+        Err(e) => check(false, format!("Account {} is inaccessible: {}", account, e))?,
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{} check(s) failed", failures);
+    }
+    cli.show_success("Everything looks good!")?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Local};
+use std::time::Duration;
+use thiserror::Error;
+
+pub type Result<T, E = DisforkError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum DisforkError {
+    #[error("{0}")]
+    Auth(String),
+
+    #[error("GitHub API rate limit exceeded{}", retry_after_hint(.retry_after))]
+    RateLimited { retry_after: Option<Duration> },
+
+    #[error("GitHub API rate limit exhausted; resets {}", reset_hint(.reset_at))]
+    RateLimitExhausted { reset_at: DateTime<Local> },
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Unavailable for legal reasons: {0}")]
+    Unavailable(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Network error: {0}")]
+    Network(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("GitHub API error ({status}): {message}")]
+    Api { status: u16, message: String },
+}
+
+impl DisforkError {
+    /// The resource was deleted or taken down (e.g. by a DMCA notice)
+    pub fn is_gone(&self) -> bool {
+        matches!(self, Self::NotFound(_) | Self::Unavailable(_))
+    }
+}
+
+fn retry_after_hint(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(after) => format!(", retry after {}s", after.as_secs()),
+        None => String::new(),
+    }
+}
+
+fn reset_hint(reset_at: &DateTime<Local>) -> String {
+    let minutes = (*reset_at - Local::now()).num_minutes().max(0);
+    let time = reset_at.format("%H:%M");
+    match minutes {
+        0 => format!("in less than a minute (at {})", time),
+        1 => format!("in 1 minute (at {})", time),
+        _ => format!("in {} minutes (at {})", minutes, time),
+    }
+}
+
+impl From<octocrab::Error> for DisforkError {
+    fn from(err: octocrab::Error) -> Self {
+        let octocrab::Error::GitHub { source, .. } = err else {
+            return Self::Network(Box::new(err));
+        };
+
+        let message = source.message;
+        match source.status_code.as_u16() {
+            401 => Self::Auth(message),
+            429 => Self::RateLimited { retry_after: None },
+            403 if message.to_ascii_lowercase().contains("rate limit") => {
+                Self::RateLimited { retry_after: None }
+            }
+            403 => Self::PermissionDenied(message),
+            404 => Self::NotFound(message),
+            451 => Self::Unavailable(message),
+            status => Self::Api { status, message },
+        }
+    }
+}
+
+impl From<reqwest::Error> for DisforkError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Network(Box::new(err))
+    }
+}
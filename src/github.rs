@@ -1,14 +1,56 @@
-use anyhow::Result;
-use octocrab::models::{Repository, repos::Branch};
-use octocrab::{Octocrab, Page};
+use crate::error::{DisforkError, Result};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use clap::ValueEnum;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use octocrab::models::{
+    IssueState, RateLimit, Repository,
+    pulls::PullRequest,
+    repos::{Branch, Tag},
+};
+use octocrab::{Octocrab, Page, params};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Semaphore;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+#[cfg(test)]
+pub mod fake;
+#[cfg(test)]
+mod mock;
 
 #[derive(Debug, Clone)]
 pub struct GitHubClient {
     pub octocrab: Octocrab,
+    /// For requests outside the API, e.g. to wiki git repos
+    token: Token,
     semaphore: Arc<Semaphore>,
+    max_retries: u32,
+    /// Testing hook: fail every Nth API request as rate limited
+    simulate_rate_limit: Option<u64>,
+    request_count: Arc<AtomicU64>,
+    /// The token can't list codespaces, warned about once and not asked again
+    codespaces_denied: Arc<AtomicBool>,
+}
+
+/// Keeps the token out of debug output
+#[derive(Clone)]
+struct Token(Arc<str>);
+
+impl std::fmt::Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Token(..)")
+    }
+}
+
+/// Which endpoint to list an account's repositories from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AccountType {
+    User,
+    Org,
+    /// Detect from the account profile
+    Auto,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +62,234 @@ pub struct DeviceCode {
     pub interval: u64,
 }
 
+/// Short-lived token to act as a GitHub App installation
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstallationToken {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl InstallationToken {
+    /// Whether the token should be replaced before starting more work
+    pub fn expires_soon(&self) -> bool {
+        self.expires_at - Utc::now() < TimeDelta::minutes(10)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareStatus {
+    Ahead,
+    Behind,
+    Diverged,
+    Identical,
+}
+
+/// A commit of the compared head that's not in the base
+#[derive(Debug, Clone, Serialize)]
+pub struct ComparedCommit {
+    pub sha: String,
+    pub parent_count: usize,
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub ahead_by: i64,
+    pub behind_by: i64,
+    pub status: CompareStatus,
+    /// Commits ahead of the base, empty if the compare API didn't provide them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub commits: Vec<ComparedCommit>,
+}
+
+impl Comparison {
+    pub fn new(ahead_by: i64, behind_by: i64, commits: Vec<ComparedCommit>) -> Self {
+        let status = match (ahead_by > 0, behind_by > 0) {
+            (true, true) => CompareStatus::Diverged,
+            (true, false) => CompareStatus::Ahead,
+            (false, true) => CompareStatus::Behind,
+            (false, false) => CompareStatus::Identical,
+        };
+
+        Self {
+            ahead_by,
+            behind_by,
+            status,
+            commits,
+        }
+    }
+
+    /// Commits ahead that are original work: merge commits (e.g. merging upstream back
+    /// in) don't count, nor do commits by bots with `ignore_bots`. Plain `ahead_by` if
+    /// the compare API didn't list every commit ahead.
+    pub fn original_ahead(&self, ignore_bots: bool) -> i64 {
+        if self.commits.len() as i64 != self.ahead_by {
+            return self.ahead_by;
+        }
+
+        self.commits
+            .iter()
+            .filter(|commit| commit.parent_count < 2)
+            .filter(|commit| {
+                !(ignore_bots
+                    && commit
+                        .author
+                        .as_deref()
+                        .is_some_and(|author| author.ends_with("[bot]")))
+            })
+            .count() as i64
+    }
+}
+
+/// Outcome of a successful [`GitHubClient::delete_repo`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deletion {
+    Deleted,
+    /// The repository didn't exist anymore
+    AlreadyGone,
+}
+
+#[derive(Debug, Clone)]
+pub struct Repositories {
+    pub items: Vec<Repository>,
+    /// Listing stopped at the limit, there are more repositories than `items`
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Branches {
+    pub items: Vec<Branch>,
+    /// Listing stopped at the limit, there are more branches than `items`
+    pub truncated: bool,
+}
+
+/// The subset of the GitHub API needed to analyze forks
+pub trait GitHubApi: Clone + Send + Sync + 'static {
+    /// Renamed repositories are found under their old name too, GitHub answers with a
+    /// redirect to the new location which is followed
+    fn get_repo(&self, owner: &str, repo: &str) -> impl Future<Output = Result<Repository>> + Send;
+
+    /// Lists branches, stopping once more than `limit` are collected
+    fn list_branches(
+        &self,
+        owner: &str,
+        repo: &str,
+        limit: usize,
+    ) -> impl Future<Output = Result<Branches>> + Send;
+
+    fn get_branch(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+    ) -> impl Future<Output = Result<Branch>> + Send;
+
+    fn has_tags(&self, owner: &str, repo: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Commit SHAs of all tags by tag name
+    fn list_tags(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> impl Future<Output = Result<HashMap<String, String>>> + Send;
+
+    fn has_releases(&self, owner: &str, repo: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    fn list_topics(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Head labels (`owner:branch`) of the repo's open pull requests
+    fn open_pull_heads(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Head commit SHAs of the repo's merged pull requests from `head` (`owner:branch`)
+    fn merged_pull_heads(
+        &self,
+        owner: &str,
+        repo: &str,
+        head: &str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    /// Whether the repo has a GitHub Pages site set up
+    fn has_pages_site(&self, owner: &str, repo: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Whether the repo has any webhook or deploy key configured, an error if the token
+    /// may not see them
+    fn has_automation(&self, owner: &str, repo: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Whether the repo has any Actions secret, Actions variable or environment, an error
+    /// if the token may not see them
+    fn has_actions_config(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Whether the repo's wiki has any page, its git repo only exists once it does
+    fn has_wiki_pages(&self, owner: &str, repo: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Whether an open pull request in any repo has its head at `sha` in this repo
+    fn has_open_pull_from(
+        &self,
+        owner: &str,
+        repo: &str,
+        sha: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Whether any Actions workflow ran since `since`
+    fn has_workflow_runs_since(
+        &self,
+        owner: &str,
+        repo: &str,
+        since: DateTime<Utc>,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Whether the user has any codespace of the repo, `false` if the token may not see
+    /// codespaces, which is warned about once
+    fn has_codespaces(&self, owner: &str, repo: &str) -> impl Future<Output = Result<bool>> + Send;
+
+    fn has_deployments(&self, owner: &str, repo: &str)
+    -> impl Future<Output = Result<bool>> + Send;
+
+    fn is_branch_protected(
+        &self,
+        owner: &str,
+        repo: &str,
+        branch: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Whether the repo has open issues or any discussion, which GitHub only counts
+    /// where they're enabled
+    fn has_issue_activity(
+        &self,
+        owner: &str,
+        repo: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// Names of the `count` branches with the most recent commits
+    fn recent_branches(
+        &self,
+        owner: &str,
+        repo: &str,
+        count: usize,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send;
+
+    fn compare_commits(
+        &self,
+        owner: &str,
+        repo: &str,
+        base: &str,
+        head: &str,
+    ) -> impl Future<Output = Result<Comparison>> + Send;
+}
+
 impl GitHubClient {
     pub async fn start_device_flow(client_id: &str) -> Result<DeviceCode> {
         let client = reqwest::Client::new();
@@ -39,21 +309,35 @@ impl GitHubClient {
         device_code: &str,
         interval: u64,
         expires_in: u64,
+        timeout: Option<u64>,
     ) -> Result<String> {
         let client = reqwest::Client::new();
         let start = tokio::time::Instant::now();
-        let expires_after = std::time::Duration::from_secs(expires_in);
+        let (limit, timed_out) = match timeout {
+            Some(timeout) if timeout < expires_in => (
+                timeout,
+                format!("Not authorized within the {}s --auth-timeout", timeout),
+            ),
+            _ => (
+                expires_in,
+                format!(
+                    "Authorization timed out after {} seconds, the device code expired",
+                    expires_in
+                ),
+            ),
+        };
+        let expires_after = std::time::Duration::from_secs(limit);
         let mut poll_interval = interval;
 
         loop {
             if start.elapsed() >= expires_after {
-                anyhow::bail!("Authorization timed out after {} seconds", expires_in);
+                return Err(DisforkError::Auth(timed_out));
             }
 
             tokio::time::sleep(std::time::Duration::from_secs(poll_interval)).await;
 
             if start.elapsed() >= expires_after {
-                anyhow::bail!("Authorization timed out after {} seconds", expires_in);
+                return Err(DisforkError::Auth(timed_out));
             }
 
             let response = client
@@ -88,24 +372,257 @@ impl GitHubClient {
                         continue;
                     }
                     "expired_token" => {
-                        anyhow::bail!("Device code expired. Please restart authorization.")
+                        return Err(DisforkError::Auth(
+                            "Device code expired. Please restart authorization.".to_string(),
+                        ));
+                    }
+                    "access_denied" => {
+                        return Err(DisforkError::Auth(
+                            "Authorization denied on GitHub device flow.".to_string(),
+                        ));
+                    }
+                    _ => {
+                        return Err(DisforkError::Auth(format!(
+                            "Authorization failed: {}",
+                            error
+                        )));
                     }
-                    "access_denied" => anyhow::bail!("Authorization denied on GitHub device flow."),
-                    _ => anyhow::bail!("Authorization failed: {}", error),
                 }
             }
 
             if let Some(description) = result.error_description {
-                anyhow::bail!("Authorization failed: {}", description);
+                return Err(DisforkError::Auth(format!(
+                    "Authorization failed: {}",
+                    description
+                )));
             }
         }
     }
 
-    pub fn new(token: String, parallel: usize) -> Result<Self> {
-        let octocrab = Octocrab::builder().personal_token(token).build()?;
+    /// Mints an installation token with a JWT signed by the app's private key
+    pub async fn mint_installation_token(
+        app_id: u64,
+        private_key: &[u8],
+        installation_id: u64,
+    ) -> Result<InstallationToken> {
+        #[derive(Serialize)]
+        struct Claims {
+            iat: i64,
+            exp: i64,
+            iss: String,
+        }
+
+        let now = Utc::now().timestamp();
+        // Backdated against clock drift, GitHub rejects JWTs living over 10 minutes
+        let claims = Claims {
+            iat: now - 60,
+            exp: now + 9 * 60,
+            iss: app_id.to_string(),
+        };
+        let key = EncodingKey::from_rsa_pem(private_key)
+            .map_err(|e| DisforkError::Auth(format!("Invalid app private key: {}", e)))?;
+        let jwt = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)
+            .map_err(|e| DisforkError::Auth(format!("Failed to sign app JWT: {}", e)))?;
+
+        let response = reqwest::Client::new()
+            .post(format!(
+                "https://api.github.com/app/installations/{}/access_tokens",
+                installation_id
+            ))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "disfork")
+            .bearer_auth(jwt)
+            .send()
+            .await?;
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(match status {
+                401 => DisforkError::Auth(message),
+                404 => DisforkError::NotFound(message),
+                _ => DisforkError::Api { status, message },
+            });
+        }
+
+        let token: InstallationToken = response.json().await?;
+        Ok(token)
+    }
+
+    pub fn new(token: String, parallel: usize, max_retries: u32) -> Result<Self> {
+        let octocrab = Octocrab::builder().personal_token(token.clone()).build()?;
         let semaphore = Arc::new(Semaphore::new(parallel));
 
-        Ok(Self { octocrab, semaphore })
+        Ok(Self {
+            octocrab,
+            token: Token(token.into()),
+            semaphore,
+            max_retries,
+            simulate_rate_limit: None,
+            request_count: Arc::new(AtomicU64::new(0)),
+            codespaces_denied: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Makes every Nth API request fail as if rate limited, for testing only
+    pub fn with_simulated_rate_limit(mut self, every: Option<u64>) -> Self {
+        self.simulate_rate_limit = every.filter(|&n| n > 0);
+        self
+    }
+
+    /// Waits for a slot among the parallel requests
+    async fn permit(&self) -> Result<SemaphorePermit<'_>> {
+        let permit = self
+            .semaphore
+            .acquire()
+            .await
+            .expect("request semaphore is never closed");
+
+        let count = self.request_count.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(every) = self.simulate_rate_limit
+            && count.is_multiple_of(every)
+        {
+            return Err(DisforkError::RateLimited {
+                retry_after: Some(Duration::from_secs(1)),
+            });
+        }
+
+        Ok(permit)
+    }
+
+    /// Sends a request in one of the parallel slots, retrying it while rate limited.
+    /// Waits as long as GitHub asks, or backs off exponentially, the slot is freed meanwhile.
+    async fn request<T, E, F, Fut>(&self, send: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = std::result::Result<T, E>>,
+        DisforkError: From<E>,
+    {
+        let mut attempt = 0;
+
+        loop {
+            let result = match self.permit().await {
+                Ok(_permit) => send().await.map_err(DisforkError::from),
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(DisforkError::RateLimited { retry_after }) if attempt < self.max_retries => {
+                    attempt += 1;
+                    let wait = retry_after.unwrap_or(Duration::from_secs(1 << attempt));
+                    tracing::warn!(
+                        "Rate limited, retrying in {}s (attempt {}/{})",
+                        wait.as_secs(),
+                        attempt,
+                        self.max_retries
+                    );
+                    tokio::time::sleep(wait).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Checks that the GitHub API is reachable at all, no token needed
+    pub async fn check_connectivity() -> Result<()> {
+        reqwest::Client::new()
+            .get("https://api.github.com/")
+            .header("User-Agent", "disfork")
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Scopes granted to a classic token, `None` for tokens without scopes (e.g. App tokens)
+    pub async fn token_scopes(&self) -> Result<Option<String>> {
+        let response = self.octocrab._get("/user").await?;
+        let scopes = response
+            .headers()
+            .get("x-oauth-scopes")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(scopes)
+    }
+
+    pub async fn rate_limit(&self) -> Result<RateLimit> {
+        let rate_limit = self.octocrab.ratelimit().get().await?;
+        Ok(rate_limit)
+    }
+
+    /// Lowercase full names of the repos pinned on the account's profile
+    pub async fn pinned_repos(&self, login: &str) -> Result<Vec<String>> {
+        const QUERY: &str = r#"
+            query($login: String!) {
+                repositoryOwner(login: $login) {
+                    ... on ProfileOwner {
+                        pinnedItems(first: 6, types: REPOSITORY) {
+                            nodes { ... on Repository { nameWithOwner } }
+                        }
+                    }
+                }
+            }
+        "#;
+
+        let query = serde_json::json!({
+            "query": QUERY,
+            "variables": { "login": login },
+        });
+        let response: serde_json::Value = self.request(|| self.octocrab.graphql(&query)).await?;
+
+        let names = response["data"]["repositoryOwner"]["pinnedItems"]["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|node| node["nameWithOwner"].as_str().map(str::to_lowercase))
+            .collect();
+        Ok(names)
+    }
+
+    /// Lowercase full names of the GitHub repos the repo's `.gitmodules` points at
+    pub async fn submodule_repos(&self, owner: &str, repo: &str) -> Result<Vec<String>> {
+        let content = match self
+            .request(|| async {
+                self.octocrab
+                    .repos(owner, repo)
+                    .get_content()
+                    .path(".gitmodules")
+                    .send()
+                    .await
+            })
+            .await
+        {
+            Ok(mut content) => content.take_items(),
+            // No submodules, or an empty repo
+            Err(e) if e.is_gone() => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        Ok(content
+            .first()
+            .and_then(|file| file.decoded_content())
+            .map(|gitmodules| parse_gitmodules(&gitmodules, owner))
+            .unwrap_or_default())
+    }
+
+    /// When the core quota is used up, the local time it resets at
+    pub async fn rate_limit_reset(&self) -> Result<Option<DateTime<Local>>> {
+        // Querying the rate limit doesn't count against it
+        let response = self.octocrab._get("/rate_limit").await?;
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<i64>().ok())
+        };
+
+        if header("x-ratelimit-remaining") != Some(0) {
+            return Ok(None);
+        }
+        let reset_at = header("x-ratelimit-reset")
+            .and_then(|reset| DateTime::from_timestamp(reset, 0))
+            .map(|reset| reset.with_timezone(&Local));
+        Ok(reset_at)
     }
 
     pub async fn current_user(&self) -> Result<String> {
@@ -113,124 +630,922 @@ impl GitHubClient {
         Ok(user.login)
     }
 
-    pub async fn list_repos(&self, owner: &str) -> Result<Vec<Repository>> {
+    /// Lowercased account type, e.g. `user` or `organization`
+    pub async fn account_type(&self, owner: &str) -> Result<String> {
         let profile = self.octocrab.users(owner).profile().await?;
-        let account_type = profile.r#type.to_ascii_lowercase();
+        Ok(profile.r#type.to_ascii_lowercase())
+    }
 
-        if account_type == "organization" || account_type == "enterprise" {
-            self.list_org_repos(owner).await
+    /// Lists the account's repositories, stopping once more than `limit` are collected
+    pub async fn list_repos(
+        &self,
+        owner: &str,
+        account_type: AccountType,
+        limit: Option<usize>,
+    ) -> Result<Repositories> {
+        if self.is_org(owner, account_type).await? {
+            self.list_org_repos(owner, limit).await
         } else {
-            self.list_user_repos(owner).await
+            self.list_user_repos(owner, limit).await
         }
     }
 
-    async fn list_user_repos(&self, owner: &str) -> Result<Vec<Repository>> {
-        let mut repos = Vec::new();
-        let mut page = 1u32;
+    async fn is_org(&self, owner: &str, account_type: AccountType) -> Result<bool> {
+        Ok(match account_type {
+            AccountType::User => false,
+            AccountType::Org => true,
+            AccountType::Auto => {
+                let account_type = self.account_type(owner).await?;
+                account_type == "organization" || account_type == "enterprise"
+            }
+        })
+    }
 
-        loop {
-            let page_data: Page<Repository> = self
-                .octocrab
-                .users(owner)
-                .repos()
-                .per_page(100)
-                .page(page)
-                .send()
-                .await?;
+    /// Lowercase full names of the repos the account's packages were published from,
+    /// empty if the token lacks the `read:packages` scope
+    pub async fn package_repos(
+        &self,
+        owner: &str,
+        account_type: AccountType,
+    ) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Package {
+            repository: Option<PackageRepository>,
+        }
+        #[derive(Deserialize)]
+        struct PackageRepository {
+            full_name: String,
+        }
 
-            repos.extend(page_data.items);
+        let scope = if self.is_org(owner, account_type).await? {
+            "orgs"
+        } else {
+            "users"
+        };
+        let mut repos = Vec::new();
+        // The API only lists one package type at a time
+        for package_type in ["npm", "maven", "rubygems", "docker", "nuget", "container"] {
+            for page in 1.. {
+                let url = format!("/{}/{}/packages", scope, owner);
+                let query = [
+                    ("package_type", package_type.to_string()),
+                    ("per_page", "100".to_string()),
+                    ("page", page.to_string()),
+                ];
+                let packages: Vec<Package> =
+                    match self.request(|| self.octocrab.get(&url, Some(&query))).await {
+                        Ok(packages) => packages,
+                        // Accounts without any package of the type
+                        Err(DisforkError::NotFound(_)) => break,
+                        Err(DisforkError::PermissionDenied(_) | DisforkError::Auth(_)) => {
+                            return Ok(Vec::new());
+                        }
+                        Err(e) => return Err(e),
+                    };
 
-            if page_data.next.is_none() {
-                break;
+                let last = packages.len() < 100;
+                repos.extend(
+                    packages
+                        .into_iter()
+                        .filter_map(|package| package.repository)
+                        .map(|repo| repo.full_name.to_lowercase()),
+                );
+                if last {
+                    break;
+                }
             }
-            page += 1;
         }
-
         Ok(repos)
     }
 
-    async fn list_org_repos(&self, owner: &str) -> Result<Vec<Repository>> {
+    async fn list_user_repos(&self, owner: &str, limit: Option<usize>) -> Result<Repositories> {
+        let first = self
+            .octocrab
+            .users(owner)
+            .repos()
+            .per_page(100)
+            .send()
+            .await?;
+        self.follow_repo_pages(first, limit).await
+    }
+
+    async fn list_org_repos(&self, owner: &str, limit: Option<usize>) -> Result<Repositories> {
+        let first = self
+            .octocrab
+            .orgs(owner)
+            .list_repos()
+            .per_page(100)
+            .send()
+            .await?;
+        self.follow_repo_pages(first, limit).await
+    }
+
+    /// Collects repositories from `page` on, following each page's `next` link
+    async fn follow_repo_pages(
+        &self,
+        mut page: Page<Repository>,
+        limit: Option<usize>,
+    ) -> Result<Repositories> {
         let mut repos = Vec::new();
-        let mut page = 1u32;
 
         loop {
-            let page_data: Page<Repository> = self
-                .octocrab
-                .orgs(owner)
-                .list_repos()
-                .per_page(100)
-                .page(page)
-                .send()
-                .await?;
+            repos.extend(page.take_items());
 
-            repos.extend(page_data.items);
+            if let Some(limit) = limit
+                && repos.len() > limit
+            {
+                repos.truncate(limit);
+                return Ok(Repositories {
+                    items: repos,
+                    truncated: true,
+                });
+            }
+            match self.octocrab.get_page(&page.next).await? {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
 
-            if page_data.next.is_none() {
-                break;
+        Ok(Repositories {
+            items: repos,
+            truncated: false,
+        })
+    }
+
+    pub async fn unarchive_repo(&self, owner: &str, repo: &str) -> Result<()> {
+        let url = format!("/repos/{}/{}", owner, repo);
+        let _: Repository = self
+            .octocrab
+            .patch(&url, Some(&serde_json::json!({ "archived": false })))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_branch(&self, owner: &str, repo: &str, branch: &str) -> Result<Deletion> {
+        let reference = params::repos::Reference::Branch(branch.to_string());
+        match self
+            .octocrab
+            .repos(owner, repo)
+            .delete_ref(&reference)
+            .await
+            .map_err(DisforkError::from)
+        {
+            Ok(()) => Ok(Deletion::Deleted),
+            // GitHub answers 422 rather than 404 for refs that don't exist
+            Err(DisforkError::NotFound(_) | DisforkError::Api { status: 422, .. }) => {
+                Ok(Deletion::AlreadyGone)
             }
-            page += 1;
+            Err(e) => Err(e),
         }
+    }
 
-        Ok(repos)
+    pub async fn delete_repo(&self, owner: &str, repo: &str) -> Result<Deletion> {
+        let mut attempt = 0;
+
+        loop {
+            let err = match self.octocrab.repos(owner, repo).delete().await {
+                Ok(()) => return Ok(Deletion::Deleted),
+                Err(err) => err,
+            };
+
+            let err = DisforkError::from(err);
+            // Deleted elsewhere in the meantime, e.g. by hand or another run
+            if attempt == 0 && matches!(err, DisforkError::NotFound(_)) {
+                return Ok(Deletion::AlreadyGone);
+            }
+            let is_transient = matches!(err, DisforkError::Api { status, .. } if status >= 500);
+            if !is_transient || attempt >= self.max_retries {
+                return Err(err);
+            }
+            attempt += 1;
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+
+            // A 5xx doesn't mean the deletion didn't go through, check before retrying
+            if let Err(err) = self.octocrab.repos(owner, repo).get().await
+                && matches!(err.into(), DisforkError::NotFound(_))
+            {
+                return Ok(Deletion::Deleted);
+            }
+        }
     }
+}
 
-    pub async fn get_repo(&self, owner: &str, repo: &str) -> Result<Repository> {
-        let _permit = self.semaphore.acquire().await?;
-        let repo = self.octocrab.repos(owner, repo).get().await?;
-        Ok(repo)
+impl GitHubApi for GitHubClient {
+    async fn get_repo(&self, owner: &str, repo: &str) -> Result<Repository> {
+        self.request(|| async { self.octocrab.repos(owner, repo).get().await })
+            .await
     }
 
-    pub async fn list_branches(&self, owner: &str, repo: &str) -> Result<Vec<Branch>> {
+    async fn list_branches(&self, owner: &str, repo: &str, limit: usize) -> Result<Branches> {
         let mut branches = Vec::new();
-        let mut page = 1u32;
+        // One request per page to ensure fair distribution of HTTP requests
+        let mut page: Page<Branch> = self
+            .request(|| async {
+                self.octocrab
+                    .repos(owner, repo)
+                    .list_branches()
+                    .per_page(100)
+                    .send()
+                    .await
+            })
+            .await?;
 
         loop {
-            // Acquire permit per page to ensure fair distribution of HTTP requests
-            let _permit = self.semaphore.acquire().await?;
-            let page_data: Page<Branch> = self
-                .octocrab
-                .repos(owner, repo)
-                .list_branches()
-                .per_page(100)
-                .page(page)
-                .send()
-                .await?;
+            branches.extend(page.take_items());
+
+            if page.next.is_none() {
+                break;
+            }
+            // No need to walk every page of a repo that's over the limit anyway
+            if branches.len() > limit {
+                return Ok(Branches {
+                    items: branches,
+                    truncated: true,
+                });
+            }
+
+            match self.request(|| self.octocrab.get_page(&page.next)).await? {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+
+        Ok(Branches {
+            items: branches,
+            truncated: false,
+        })
+    }
+
+    async fn get_branch(&self, owner: &str, repo: &str, branch: &str) -> Result<Branch> {
+        let url = format!("/repos/{}/{}/branches/{}", owner, repo, branch);
+        self.request(|| self.octocrab.get(&url, None::<&()>)).await
+    }
+
+    async fn has_tags(&self, owner: &str, repo: &str) -> Result<bool> {
+        let page = self
+            .request(|| async {
+                self.octocrab
+                    .repos(owner, repo)
+                    .list_tags()
+                    .per_page(1)
+                    .send()
+                    .await
+            })
+            .await?;
+        Ok(!page.items.is_empty())
+    }
+
+    async fn list_tags(&self, owner: &str, repo: &str) -> Result<HashMap<String, String>> {
+        let mut tags = HashMap::new();
+        let mut page: Page<Tag> = self
+            .request(|| async {
+                self.octocrab
+                    .repos(owner, repo)
+                    .list_tags()
+                    .per_page(100)
+                    .send()
+                    .await
+            })
+            .await?;
+
+        loop {
+            tags.extend(
+                page.take_items()
+                    .into_iter()
+                    .map(|tag| (tag.name, tag.commit.sha)),
+            );
+
+            if page.next.is_none() {
+                break;
+            }
+            match self.request(|| self.octocrab.get_page(&page.next)).await? {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+
+        Ok(tags)
+    }
+
+    async fn has_releases(&self, owner: &str, repo: &str) -> Result<bool> {
+        let page = self
+            .request(|| async {
+                self.octocrab
+                    .repos(owner, repo)
+                    .releases()
+                    .list()
+                    .per_page(1)
+                    .send()
+                    .await
+            })
+            .await?;
+        Ok(!page.items.is_empty())
+    }
+
+    async fn list_topics(&self, owner: &str, repo: &str) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Topics {
+            names: Vec<String>,
+        }
+
+        let url = format!("/repos/{}/{}/topics", owner, repo);
+        let topics: Topics = self
+            .request(|| self.octocrab.get(&url, None::<&()>))
+            .await?;
+        Ok(topics.names)
+    }
 
-            let has_next = page_data.next.is_some();
-            branches.extend(page_data.items);
+    async fn open_pull_heads(&self, owner: &str, repo: &str) -> Result<Vec<String>> {
+        let mut heads = Vec::new();
+        let mut page: Page<PullRequest> = self
+            .request(|| async {
+                self.octocrab
+                    .pulls(owner, repo)
+                    .list()
+                    .state(params::State::Open)
+                    .per_page(100)
+                    .send()
+                    .await
+            })
+            .await?;
 
-            if !has_next {
+        loop {
+            heads.extend(page.take_items().into_iter().filter_map(|pr| pr.head.label));
+
+            if page.next.is_none() {
                 break;
             }
-            page += 1;
+            match self.request(|| self.octocrab.get_page(&page.next)).await? {
+                Some(next) => page = next,
+                None => break,
+            }
+        }
+
+        Ok(heads)
+    }
+
+    async fn merged_pull_heads(&self, owner: &str, repo: &str, head: &str) -> Result<Vec<String>> {
+        let page = self
+            .request(|| async {
+                self.octocrab
+                    .pulls(owner, repo)
+                    .list()
+                    .state(params::State::Closed)
+                    .head(head)
+                    .per_page(100)
+                    .send()
+                    .await
+            })
+            .await?;
+        Ok(page
+            .items
+            .into_iter()
+            .filter(|pr| pr.merged_at.is_some())
+            .map(|pr| pr.head.sha)
+            .collect())
+    }
+
+    async fn has_pages_site(&self, owner: &str, repo: &str) -> Result<bool> {
+        let url = format!("/repos/{}/{}/pages", owner, repo);
+        match self
+            .request(|| {
+                self.octocrab
+                    .get::<serde_json::Value, _, _>(&url, None::<&()>)
+            })
+            .await
+        {
+            Ok(_) => Ok(true),
+            // Fine-grained and App tokens may lack Pages read access, which is an error
+            // rather than no site so the fork is kept to be safe
+            Err(DisforkError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn has_automation(&self, owner: &str, repo: &str) -> Result<bool> {
+        for kind in ["hooks", "keys"] {
+            let url = format!("/repos/{}/{}/{}", owner, repo, kind);
+            match self
+                .request(|| {
+                    self.octocrab
+                        .get::<Vec<serde_json::Value>, _, _>(&url, Some(&[("per_page", 1)]))
+                })
+                .await
+            {
+                Ok(items) if !items.is_empty() => return Ok(true),
+                Ok(_) | Err(DisforkError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(false)
+    }
+
+    async fn has_actions_config(&self, owner: &str, repo: &str) -> Result<bool> {
+        #[derive(Deserialize)]
+        struct Count {
+            total_count: u64,
+        }
+
+        for kind in ["actions/secrets", "actions/variables", "environments"] {
+            let url = format!("/repos/{}/{}/{}", owner, repo, kind);
+            match self
+                .request(|| {
+                    self.octocrab
+                        .get::<Count, _, _>(&url, Some(&[("per_page", 1)]))
+                })
+                .await
+            {
+                Ok(count) if count.total_count > 0 => return Ok(true),
+                Ok(_) | Err(DisforkError::NotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(false)
+    }
+
+    async fn has_wiki_pages(&self, owner: &str, repo: &str) -> Result<bool> {
+        let url = format!(
+            "https://github.com/{}/{}.wiki.git/info/refs?service=git-upload-pack",
+            owner, repo
+        );
+        let response = self
+            .request(|| {
+                reqwest::Client::new()
+                    .get(&url)
+                    .basic_auth("x-access-token", Some(&*self.token.0))
+                    .send()
+            })
+            .await?;
+        Ok(response.status().is_success())
+    }
+
+    async fn has_open_pull_from(&self, owner: &str, repo: &str, sha: &str) -> Result<bool> {
+        let url = format!("/repos/{}/{}/commits/{}/pulls", owner, repo, sha);
+        let pulls: Vec<PullRequest> = self
+            .request(|| self.octocrab.get(&url, None::<&()>))
+            .await?;
+
+        let full_name = format!("{}/{}", owner, repo);
+        Ok(pulls.iter().any(|pr| {
+            pr.state == Some(IssueState::Open)
+                && pr
+                    .head
+                    .repo
+                    .as_ref()
+                    .and_then(|head| head.full_name.as_deref())
+                    .is_some_and(|head| head.eq_ignore_ascii_case(&full_name))
+        }))
+    }
+
+    async fn has_workflow_runs_since(
+        &self,
+        owner: &str,
+        repo: &str,
+        since: DateTime<Utc>,
+    ) -> Result<bool> {
+        #[derive(Deserialize)]
+        struct Runs {
+            total_count: u64,
+        }
+
+        let url = format!("/repos/{}/{}/actions/runs", owner, repo);
+        let created = format!(">={}", since.format("%Y-%m-%dT%H:%M:%SZ"));
+        let query = [("created", created.as_str()), ("per_page", "1")];
+        let runs: Runs = self
+            .request(|| self.octocrab.get(&url, Some(&query)))
+            .await?;
+        Ok(runs.total_count > 0)
+    }
+
+    async fn has_codespaces(&self, owner: &str, repo: &str) -> Result<bool> {
+        #[derive(Deserialize)]
+        struct Codespaces {
+            total_count: u64,
+        }
+
+        if self.codespaces_denied.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let url = format!("/repos/{}/{}/codespaces", owner, repo);
+        match self
+            .request(|| {
+                self.octocrab
+                    .get::<Codespaces, _, _>(&url, Some(&[("per_page", 1)]))
+            })
+            .await
+        {
+            Ok(codespaces) => Ok(codespaces.total_count > 0),
+            // Tokens with the usual repo scopes can't see codespaces at all, failing every
+            // fork's check would keep them all
+            Err(DisforkError::NotFound(_) | DisforkError::PermissionDenied(_)) => {
+                if !self.codespaces_denied.swap(true, Ordering::Relaxed) {
+                    tracing::warn!(
+                        "The token can't list codespaces (needs the codespace scope), forks \
+                         with codespaces won't be kept for them"
+                    );
+                }
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn has_deployments(&self, owner: &str, repo: &str) -> Result<bool> {
+        let url = format!("/repos/{}/{}/deployments", owner, repo);
+        let deployments: Vec<serde_json::Value> = self
+            .request(|| self.octocrab.get(&url, Some(&[("per_page", 1)])))
+            .await?;
+        Ok(!deployments.is_empty())
+    }
+
+    async fn is_branch_protected(&self, owner: &str, repo: &str, branch: &str) -> Result<bool> {
+        let url = format!("/repos/{}/{}/branches/{}/protection", owner, repo, branch);
+        match self
+            .request(|| {
+                self.octocrab
+                    .get::<serde_json::Value, _, _>(&url, None::<&()>)
+            })
+            .await
+        {
+            Ok(_) => Ok(true),
+            // Unprotected branches have no protection to show
+            Err(DisforkError::NotFound(_)) => Ok(false),
+            Err(e) => Err(e),
         }
+    }
+
+    async fn has_issue_activity(&self, owner: &str, repo: &str) -> Result<bool> {
+        // Unlike `open_issues_count`, this doesn't count pull requests
+        const QUERY: &str = r#"
+            query($owner: String!, $name: String!) {
+                repository(owner: $owner, name: $name) {
+                    issues(states: OPEN) { totalCount }
+                    discussions { totalCount }
+                }
+            }
+        "#;
+
+        let query = serde_json::json!({
+            "query": QUERY,
+            "variables": { "owner": owner, "name": repo },
+        });
+        let response: serde_json::Value = self.request(|| self.octocrab.graphql(&query)).await?;
+
+        let repository = &response["data"]["repository"];
+        let count = |field: &str| repository[field]["totalCount"].as_u64().unwrap_or(0);
+        Ok(count("issues") > 0 || count("discussions") > 0)
+    }
+
+    async fn recent_branches(&self, owner: &str, repo: &str, count: usize) -> Result<Vec<String>> {
+        // The REST API can't order branches, GraphQL can
+        const QUERY: &str = r#"
+            query($owner: String!, $name: String!, $first: Int!) {
+                repository(owner: $owner, name: $name) {
+                    refs(
+                        refPrefix: "refs/heads/"
+                        first: $first
+                        orderBy: { field: TAG_COMMIT_DATE, direction: DESC }
+                    ) {
+                        nodes { name }
+                    }
+                }
+            }
+        "#;
+
+        let query = serde_json::json!({
+            "query": QUERY,
+            "variables": { "owner": owner, "name": repo, "first": count },
+        });
+        let response: serde_json::Value = self.request(|| self.octocrab.graphql(&query)).await?;
 
-        Ok(branches)
+        let names = response["data"]["repository"]["refs"]["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|node| node["name"].as_str().map(str::to_string))
+            .collect();
+        Ok(names)
     }
 
-    pub async fn compare_commits(
+    async fn compare_commits(
         &self,
         owner: &str,
         repo: &str,
         base: &str,
         head: &str,
-    ) -> Result<i64> {
-        let _permit = self.semaphore.acquire().await?;
-        let url = format!("/repos/{}/{}/compare/{}...{}", owner, repo, base, head);
+    ) -> Result<Comparison> {
+        let typed = self
+            .request(|| async {
+                self.octocrab
+                    .commits(owner, repo)
+                    .compare(base, head)
+                    .send()
+                    .await
+            })
+            .await;
 
-        #[derive(Deserialize)]
-        struct CompareResult {
-            ahead_by: i64,
+        match typed {
+            Ok(comparison) => {
+                let commits = comparison
+                    .commits
+                    .into_iter()
+                    .map(|commit| ComparedCommit {
+                        sha: commit.sha,
+                        parent_count: commit.parents.len(),
+                        author: commit.author.map(|author| author.login),
+                    })
+                    .collect();
+                Ok(Comparison::new(
+                    comparison.ahead_by,
+                    comparison.behind_by,
+                    commits,
+                ))
+            }
+            // The typed model is strict, fall back to only the fields we really need
+            Err(DisforkError::Network(_)) => {
+                #[derive(Deserialize)]
+                struct RawComparison {
+                    ahead_by: i64,
+                    behind_by: i64,
+                }
+
+                let url = format!("/repos/{}/{}/compare/{}...{}", owner, repo, base, head);
+                let raw: RawComparison = self
+                    .request(|| self.octocrab.get(&url, None::<&()>))
+                    .await?;
+                Ok(Comparison::new(raw.ahead_by, raw.behind_by, Vec::new()))
+            }
+            Err(e) => Err(e),
         }
+    }
+}
+
+/// Lowercase full names of the GitHub repos in a `.gitmodules` file, relative URLs are
+/// resolved against the superproject's `owner`
+fn parse_gitmodules(gitmodules: &str, owner: &str) -> Vec<String> {
+    const GITHUB_PREFIXES: [&str; 5] = [
+        "https://github.com/",
+        "http://github.com/",
+        "ssh://git@github.com/",
+        "git://github.com/",
+        "git@github.com:",
+    ];
 
-        let response: CompareResult = self.octocrab.get(&url, None::<&()>).await?;
+    gitmodules
+        .lines()
+        .filter_map(|line| {
+            let (key, url) = line.split_once('=')?;
+            if key.trim() != "url" {
+                return None;
+            }
+            let url = url.trim();
+            let path = if let Some(path) = url.strip_prefix("../../") {
+                path.to_string()
+            } else if let Some(name) = url.strip_prefix("../") {
+                format!("{}/{}", owner, name)
+            } else {
+                GITHUB_PREFIXES
+                    .iter()
+                    .find_map(|prefix| url.strip_prefix(prefix))?
+                    .to_string()
+            };
+
+            let path = path.trim_end_matches('/');
+            let path = path.strip_suffix(".git").unwrap_or(path);
+            let (repo_owner, name) = path.split_once('/')?;
+            Some(format!("{}/{}", repo_owner, name).to_lowercase())
+        })
+        .collect()
+}
 
-        Ok(response.ahead_by)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mock::MockServer;
+    use serde_json::json;
+
+    fn branches(names: &[&str]) -> serde_json::Value {
+        names
+            .iter()
+            .map(|name| serde_json::to_value(fake::branch(name, "sha")).unwrap())
+            .collect()
     }
 
-    pub async fn delete_repo(&self, owner: &str, repo: &str) -> Result<()> {
-        self.octocrab.repos(owner, repo).delete().await?;
+    fn pulls(labels: &[&str]) -> serde_json::Value {
+        labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                json!({
+                    "url": "https://api.github.com/pull",
+                    "id": i,
+                    "number": i,
+                    "locked": false,
+                    "maintainer_can_modify": false,
+                    "head": { "label": label, "ref": "branch", "sha": "sha" },
+                    "base": { "ref": "main", "sha": "sha" },
+                })
+            })
+            .collect()
+    }
 
-        Ok(())
+    fn tags(names: &[&str]) -> serde_json::Value {
+        names
+            .iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "commit": { "sha": format!("{}-sha", name), "url": "https://api.github.com/commit" },
+                    "zipball_url": "https://api.github.com/zipball",
+                    "tarball_url": "https://api.github.com/tarball",
+                    "node_id": "",
+                })
+            })
+            .collect()
+    }
+
+    const BRANCHES: &str = "/repos/me/lib/branches";
+    const PAGES: [&str; 3] = [
+        BRANCHES,
+        "/repos/me/lib/branches?page=2",
+        "/repos/me/lib/branches?page=3",
+    ];
+
+    async fn three_pages_of_branches() -> MockServer {
+        MockServer::start().await.with_pages(
+            BRANCHES,
+            vec![
+                branches(&["a", "b"]),
+                branches(&["c", "d"]),
+                branches(&["e"]),
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn list_branches_follows_every_page() {
+        let server = three_pages_of_branches().await;
+        let branches = server
+            .client(0)
+            .list_branches("me", "lib", 20)
+            .await
+            .unwrap();
+
+        let names: Vec<_> = branches.items.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c", "d", "e"]);
+        assert!(!branches.truncated);
+        assert_eq!(server.requests(), PAGES);
+    }
+
+    #[tokio::test]
+    async fn list_branches_stops_after_page_over_limit() {
+        let server = three_pages_of_branches().await;
+        let branches = server
+            .client(0)
+            .list_branches("me", "lib", 3)
+            .await
+            .unwrap();
+
+        assert_eq!(branches.items.len(), 4);
+        assert!(branches.truncated);
+        assert_eq!(server.requests(), PAGES[..2]);
+    }
+
+    #[tokio::test]
+    async fn list_branches_over_limit_on_last_page_is_complete() {
+        let server = three_pages_of_branches().await;
+        let branches = server
+            .client(0)
+            .list_branches("me", "lib", 4)
+            .await
+            .unwrap();
+
+        assert_eq!(branches.items.len(), 5);
+        assert!(!branches.truncated);
+        assert_eq!(server.requests(), PAGES);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn simulated_rate_limits_are_retried() {
+        let server = three_pages_of_branches().await;
+        let client = server.client(1).with_simulated_rate_limit(Some(2));
+        let branches = client.list_branches("me", "lib", 20).await.unwrap();
+
+        assert_eq!(branches.items.len(), 5);
+        // The injected failures never reach the server
+        assert_eq!(server.requests(), PAGES);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_surfaces_without_retries() {
+        let server = three_pages_of_branches().await;
+        let client = server.client(0).with_simulated_rate_limit(Some(2));
+        let err = client.list_branches("me", "lib", 20).await.unwrap_err();
+
+        assert!(matches!(err, DisforkError::RateLimited { .. }));
+        assert_eq!(server.requests(), PAGES[..1]);
+    }
+
+    // GitHub's primary and secondary limits answer with a 403, octocrab already retries 429s
+    #[tokio::test(start_paused = true)]
+    async fn rate_limited_responses_are_retried_with_backoff() {
+        let server = three_pages_of_branches()
+            .await
+            .with_failures(PAGES[1], 403, 2);
+        let start = tokio::time::Instant::now();
+        let branches = server
+            .client(2)
+            .list_branches("me", "lib", 20)
+            .await
+            .unwrap();
+
+        assert_eq!(branches.items.len(), 5);
+        assert_eq!(server.requests().len(), 5);
+        // 2s, then 4s
+        assert!(start.elapsed() >= Duration::from_secs(6));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limited_responses_give_up_after_max_retries() {
+        let server = three_pages_of_branches()
+            .await
+            .with_failures(PAGES[1], 403, 3);
+        let err = server
+            .client(2)
+            .list_branches("me", "lib", 20)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, DisforkError::RateLimited { .. }));
+        assert_eq!(server.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn codespaces_denied_once_are_not_asked_again() {
+        let server = MockServer::start().await;
+        let client = server.client(0);
+
+        assert!(!client.has_codespaces("me", "a").await.unwrap());
+        assert!(!client.has_codespaces("me", "b").await.unwrap());
+        assert_eq!(server.requests(), ["/repos/me/a/codespaces"]);
+    }
+
+    #[tokio::test]
+    async fn list_repos_follows_every_page() {
+        let repo = |name: &str| serde_json::to_value(fake::repo(name)).unwrap();
+        let server = MockServer::start().await.with_pages(
+            "/users/me/repos",
+            vec![
+                json!([repo("me/a")]),
+                json!([repo("me/b")]),
+                json!([repo("me/c")]),
+            ],
+        );
+        let client = server.client(0);
+
+        let repos = client
+            .list_repos("me", AccountType::User, None)
+            .await
+            .unwrap();
+        assert_eq!(repos.items.len(), 3);
+        assert!(!repos.truncated);
+
+        let repos = client
+            .list_repos("me", AccountType::User, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(repos.items.len(), 1);
+        assert!(repos.truncated);
+        // Three pages for the full listing, two until going over the limit
+        assert_eq!(server.requests().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn list_tags_follows_every_page() {
+        let server = MockServer::start().await.with_pages(
+            "/repos/me/lib/tags",
+            vec![tags(&["v1"]), tags(&["v2"]), tags(&["v3"])],
+        );
+        let tags = server.client(0).list_tags("me", "lib").await.unwrap();
+
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags["v3"], "v3-sha");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn open_pull_heads_follows_every_page() {
+        let server = MockServer::start().await.with_pages(
+            "/repos/upstream/lib/pulls",
+            vec![pulls(&["me:a"]), pulls(&["me:b"]), pulls(&["you:c"])],
+        );
+        let heads = server
+            .client(0)
+            .open_pull_heads("upstream", "lib")
+            .await
+            .unwrap();
+
+        assert_eq!(heads, ["me:a", "me:b", "you:c"]);
+        assert_eq!(server.requests().len(), 3);
     }
 }
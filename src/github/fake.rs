@@ -0,0 +1,333 @@
+//! In-memory [`GitHubApi`] to test the analysis without network
+
+use super::{Branches, Comparison, GitHubApi};
+use crate::error::{DisforkError, Result};
+use chrono::{DateTime, Utc};
+use octocrab::models::{Repository, repos::Branch};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Repos, branches and comparisons set up by the test. Anything not set up is missing:
+/// repos and comparisons are not found, listings are empty and checks find nothing.
+#[derive(Clone, Default)]
+pub struct FakeGitHub {
+    state: Arc<Mutex<State>>,
+}
+
+/// Lowercase full name, base and head (`owner:branch`)
+type CompareKey = (String, String, String);
+
+#[derive(Default)]
+struct State {
+    /// By lowercase full name
+    repos: HashMap<String, Repository>,
+    branches: HashMap<String, Vec<Branch>>,
+    /// Ahead and behind, or the status code of the error
+    comparisons: HashMap<CompareKey, Result<(i64, i64), u16>>,
+    /// Result of the `has_*` checks by name, or the status code of the error
+    checks: HashMap<&'static str, Result<bool, u16>>,
+    /// Number of calls by method name
+    calls: HashMap<&'static str, usize>,
+    /// How long each comparison takes, holding one of the permits shared like the
+    /// client's request permits
+    compare_latency: Option<(Duration, Arc<Semaphore>)>,
+}
+
+/// A repository as listed, without `parent` or `source`
+pub fn repo(full_name: &str) -> Repository {
+    let (owner, name) = full_name.split_once('/').expect("full name is owner/name");
+    let url = |path: &str| format!("https://api.github.com/{}", path);
+    serde_json::from_value(serde_json::json!({
+        "id": 1,
+        "name": name,
+        "full_name": full_name,
+        "url": url(&format!("repos/{}", full_name)),
+        "default_branch": "main",
+        "owner": {
+            "login": owner,
+            "id": 1,
+            "node_id": "",
+            "avatar_url": url("avatar"),
+            "gravatar_id": "",
+            "url": url(&format!("users/{}", owner)),
+            "html_url": url("html"),
+            "followers_url": url("followers"),
+            "following_url": url("following"),
+            "gists_url": url("gists"),
+            "starred_url": url("starred"),
+            "subscriptions_url": url("subscriptions"),
+            "organizations_url": url("organizations"),
+            "repos_url": url("repos"),
+            "events_url": url("events"),
+            "received_events_url": url("received_events"),
+            "type": "User",
+            "site_admin": false,
+        },
+    }))
+    .expect("valid repository")
+}
+
+/// A fork of `parent` as `get_repo` returns it, with `parent` and `source`
+pub fn fork(full_name: &str, parent: &Repository) -> Repository {
+    let mut fork = repo(full_name);
+    fork.fork = Some(true);
+    fork.parent = Some(Box::new(parent.clone()));
+    fork.source = Some(Box::new(parent.clone()));
+    fork
+}
+
+pub fn branch(name: &str, sha: &str) -> Branch {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "commit": { "sha": sha, "url": "https://api.github.com/commit" },
+        "protected": false,
+    }))
+    .expect("valid branch")
+}
+
+impl FakeGitHub {
+    pub fn with_repo(self, repo: Repository) -> Self {
+        let key = repo.full_name.clone().unwrap_or_default().to_lowercase();
+        self.state().repos.insert(key, repo);
+        self
+    }
+
+    pub fn with_branches(self, full_name: &str, branches: Vec<Branch>) -> Self {
+        self.state()
+            .branches
+            .insert(full_name.to_lowercase(), branches);
+        self
+    }
+
+    pub fn with_comparison(
+        self,
+        full_name: &str,
+        base: &str,
+        head: &str,
+        ahead: i64,
+        behind: i64,
+    ) -> Self {
+        self.state().comparisons.insert(
+            (full_name.to_lowercase(), base.to_string(), head.to_string()),
+            Ok((ahead, behind)),
+        );
+        self
+    }
+
+    /// Comparing fails with this HTTP status
+    pub fn with_compare_error(self, full_name: &str, base: &str, head: &str, status: u16) -> Self {
+        self.state().comparisons.insert(
+            (full_name.to_lowercase(), base.to_string(), head.to_string()),
+            Err(status),
+        );
+        self
+    }
+
+    /// The `has_*` check of this name finds what it looks for
+    pub fn with_check(self, check: &'static str) -> Self {
+        self.state().checks.insert(check, Ok(true));
+        self
+    }
+
+    /// The `has_*` check of this name fails with this HTTP status
+    pub fn with_check_error(self, check: &'static str, status: u16) -> Self {
+        self.state().checks.insert(check, Err(status));
+        self
+    }
+
+    /// Each comparison takes `latency`, at most `permits` at once
+    pub fn with_compare_latency(self, latency: Duration, permits: usize) -> Self {
+        self.state().compare_latency = Some((latency, Arc::new(Semaphore::new(permits))));
+        self
+    }
+
+    /// Number of calls of the method of this name so far
+    pub fn calls(&self, method: &str) -> usize {
+        self.state().calls.get(method).copied().unwrap_or_default()
+    }
+
+    /// Result of a `has_*` check, nothing found unless set up otherwise
+    fn check(&self, check: &'static str) -> Result<bool> {
+        let mut state = self.state();
+        *state.calls.entry(check).or_default() += 1;
+        match state.checks.get(check) {
+            Some(&Ok(found)) => Ok(found),
+            Some(&Err(status)) => Err(error(status)),
+            None => Ok(false),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("fake state poisoned")
+    }
+}
+
+fn error(status: u16) -> DisforkError {
+    match status {
+        404 => DisforkError::NotFound("Not Found".to_string()),
+        429 => DisforkError::RateLimited { retry_after: None },
+        451 => DisforkError::Unavailable("Repository access blocked".to_string()),
+        status => DisforkError::Api {
+            status,
+            message: "Fake error".to_string(),
+        },
+    }
+}
+
+fn key(owner: &str, repo: &str) -> String {
+    format!("{}/{}", owner, repo).to_lowercase()
+}
+
+impl GitHubApi for FakeGitHub {
+    async fn get_repo(&self, owner: &str, repo: &str) -> Result<Repository> {
+        self.state()
+            .repos
+            .get(&key(owner, repo))
+            .cloned()
+            .ok_or_else(|| error(404))
+    }
+
+    async fn list_branches(&self, owner: &str, repo: &str, limit: usize) -> Result<Branches> {
+        let mut state = self.state();
+        *state.calls.entry("list_branches").or_default() += 1;
+        let mut items = state
+            .branches
+            .get(&key(owner, repo))
+            .cloned()
+            .unwrap_or_default();
+        // Pages of 100 like GitHub's, listing stops after the page going over the limit
+        let mut listed = 0;
+        while listed < items.len() {
+            listed = (listed + 100).min(items.len());
+            if listed < items.len() && listed > limit {
+                items.truncate(listed);
+                return Ok(Branches {
+                    items,
+                    truncated: true,
+                });
+            }
+        }
+        Ok(Branches {
+            items,
+            truncated: false,
+        })
+    }
+
+    async fn get_branch(&self, owner: &str, repo: &str, branch: &str) -> Result<Branch> {
+        self.state()
+            .branches
+            .get(&key(owner, repo))
+            .and_then(|branches| branches.iter().find(|b| b.name == branch))
+            .cloned()
+            .ok_or_else(|| error(404))
+    }
+
+    async fn has_tags(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_tags")
+    }
+
+    async fn list_tags(&self, _owner: &str, _repo: &str) -> Result<HashMap<String, String>> {
+        Ok(HashMap::new())
+    }
+
+    async fn has_releases(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_releases")
+    }
+
+    async fn list_topics(&self, _owner: &str, _repo: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn open_pull_heads(&self, _owner: &str, _repo: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn merged_pull_heads(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _head: &str,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn has_pages_site(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_pages_site")
+    }
+
+    async fn has_automation(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_automation")
+    }
+
+    async fn has_actions_config(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_actions_config")
+    }
+
+    async fn has_wiki_pages(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_wiki_pages")
+    }
+
+    async fn has_open_pull_from(&self, _owner: &str, _repo: &str, _sha: &str) -> Result<bool> {
+        self.check("has_open_pull_from")
+    }
+
+    async fn has_workflow_runs_since(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _since: DateTime<Utc>,
+    ) -> Result<bool> {
+        self.check("has_workflow_runs_since")
+    }
+
+    async fn has_codespaces(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_codespaces")
+    }
+
+    async fn has_deployments(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_deployments")
+    }
+
+    async fn is_branch_protected(&self, _owner: &str, _repo: &str, _branch: &str) -> Result<bool> {
+        self.check("is_branch_protected")
+    }
+
+    async fn has_issue_activity(&self, _owner: &str, _repo: &str) -> Result<bool> {
+        self.check("has_issue_activity")
+    }
+
+    async fn recent_branches(
+        &self,
+        _owner: &str,
+        _repo: &str,
+        _count: usize,
+    ) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    async fn compare_commits(
+        &self,
+        owner: &str,
+        repo: &str,
+        base: &str,
+        head: &str,
+    ) -> Result<Comparison> {
+        let latency = self.state().compare_latency.clone();
+        if let Some((latency, permits)) = latency {
+            let _permit = permits.acquire().await.expect("permits are never closed");
+            tokio::time::sleep(latency).await;
+        }
+        let comparison = self
+            .state()
+            .comparisons
+            .get(&(key(owner, repo), base.to_string(), head.to_string()))
+            .copied()
+            .unwrap_or(Err(404));
+        match comparison {
+            Ok((ahead, behind)) => Ok(Comparison::new(ahead, behind, Vec::new())),
+            Err(status) => Err(error(status)),
+        }
+    }
+}
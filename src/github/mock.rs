@@ -0,0 +1,157 @@
+//! Local HTTP server with canned GitHub API responses, to test [`GitHubClient`] requests
+
+use super::GitHubClient;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Answers `GET` requests by path, paginated with `?page=N` like GitHub.
+/// Anything not set up is a 404.
+pub struct MockServer {
+    base: String,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    /// By path, with `?page=N` for pages after the first
+    responses: HashMap<String, (u16, String)>,
+    /// Next page link by path, as for `responses`
+    next: HashMap<String, String>,
+    /// Status and count of the failures to answer with before the response, by path
+    failures: HashMap<String, (u16, usize)>,
+    /// Paths of the requests served, as for `responses`
+    requests: Vec<String>,
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind mock server");
+        let base = format!("http://{}", listener.local_addr().expect("local address"));
+        let state = Arc::new(Mutex::new(State::default()));
+
+        let served = state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, served.clone()));
+            }
+        });
+        Self { base, state }
+    }
+
+    /// Client sending its API requests to this server
+    pub fn client(&self, max_retries: u32) -> GitHubClient {
+        let mut client =
+            GitHubClient::new("token".to_string(), 4, max_retries).expect("build client");
+        client.octocrab = octocrab::Octocrab::builder()
+            .personal_token("token".to_string())
+            .base_uri(self.base.as_str())
+            .expect("valid base URI")
+            .build()
+            .expect("build octocrab");
+        client
+    }
+
+    /// Serves `pages` as JSON arrays at `path`, each linking to the next
+    pub fn with_pages(self, path: &str, pages: Vec<serde_json::Value>) -> Self {
+        let count = pages.len();
+        let mut state = self.state();
+        for (i, page) in pages.into_iter().enumerate() {
+            let key = page_key(path, i + 1);
+            if i + 1 < count {
+                let next = format!("{}{}", self.base, page_key(path, i + 2));
+                state.next.insert(key.clone(), next);
+            }
+            state.responses.insert(key, (200, page.to_string()));
+        }
+        drop(state);
+        self
+    }
+
+    /// Answers the next `count` requests for `path` with `status` and a rate limit message
+    pub fn with_failures(self, path: &str, status: u16, count: usize) -> Self {
+        self.state()
+            .failures
+            .insert(path.to_string(), (status, count));
+        self
+    }
+
+    /// Paths of the requests served so far, with `?page=N` for pages after the first
+    pub fn requests(&self) -> Vec<String> {
+        self.state().requests.clone()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("mock state poisoned")
+    }
+}
+
+fn page_key(path: &str, page: usize) -> String {
+    match page {
+        1 => path.to_string(),
+        page => format!("{}?page={}", path, page),
+    }
+}
+
+/// Path of the request target, keeping only the `page` query parameter
+fn request_key(target: &str) -> String {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let page = query
+        .split('&')
+        .find_map(|param| param.strip_prefix("page="))
+        .and_then(|page| page.parse().ok())
+        .unwrap_or(1);
+    page_key(path, page)
+}
+
+async fn serve(mut stream: TcpStream, state: Arc<Mutex<State>>) {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+        }
+    }
+    let head = String::from_utf8_lossy(&head);
+    let target = head.split_whitespace().nth(1).unwrap_or("/");
+    let key = request_key(target);
+
+    let (status, body, next) = {
+        let mut state = state.lock().expect("mock state poisoned");
+        state.requests.push(key.clone());
+        if let Some((status, count)) = state.failures.get_mut(&key)
+            && *count > 0
+        {
+            *count -= 1;
+            (
+                *status,
+                r#"{"message":"API rate limit exceeded"}"#.to_string(),
+                None,
+            )
+        } else {
+            let (status, body) = state
+                .responses
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(|| (404, r#"{"message":"Not Found"}"#.to_string()));
+            (status, body, state.next.get(&key).cloned())
+        }
+    };
+
+    let mut response = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        body.len()
+    );
+    if let Some(next) = next {
+        response.push_str(&format!("Link: <{}>; rel=\"next\"\r\n", next));
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
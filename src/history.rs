@@ -0,0 +1,105 @@
+use crate::analyzer::ForkInfo;
+use crate::report::ForkRecord;
+use anyhow::{Context, Result};
+use rusqlite::{Connection, params};
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        account TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS forks (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        full_name TEXT NOT NULL,
+        useless INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        metadata TEXT NOT NULL,
+        PRIMARY KEY (run_id, full_name)
+    );
+    CREATE TABLE IF NOT EXISTS deletions (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        full_name TEXT NOT NULL,
+        deleted_at TEXT NOT NULL,
+        success INTEGER NOT NULL,
+        error TEXT
+    );
+";
+
+/// Analysis and deletion history of every run, appended to a SQLite database
+pub struct History {
+    conn: Connection,
+    run_id: i64,
+}
+
+impl History {
+    /// Opens (or creates) the database at `path` and starts a new run
+    pub fn start(path: &Path, account: &str) -> Result<Self> {
+        let conn =
+            Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        conn.execute_batch(SCHEMA)
+            .context("Failed to initialize the history database")?;
+        conn.execute(
+            "INSERT INTO runs (started_at, account) VALUES (?1, ?2)",
+            params![chrono::Utc::now().to_rfc3339(), account],
+        )?;
+        let run_id = conn.last_insert_rowid();
+
+        Ok(Self { conn, run_id })
+    }
+
+    /// Records each fork's analysis, with every compared branch if `branches`
+    pub fn record_analysis(&mut self, fork_infos: &[ForkInfo], branches: bool) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO forks (run_id, full_name, useless, kind, metadata)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for info in fork_infos {
+                let record = ForkRecord::from(info);
+                let record = if branches {
+                    record.with_branches(info)
+                } else {
+                    record
+                };
+                let kind = serde_json::to_value(record.kind)?;
+                // Struct-like kinds serialize as `{"kind": {...}}`, keep only the tag
+                let kind = match &kind {
+                    serde_json::Value::Object(map) => map.keys().next().cloned(),
+                    serde_json::Value::String(s) => Some(s.clone()),
+                    _ => None,
+                }
+                .unwrap_or_default();
+                stmt.execute(params![
+                    self.run_id,
+                    record.full_name,
+                    record.useless,
+                    kind,
+                    serde_json::to_string(&record)?,
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Records the outcome of deleting `full_name`, `error` is `None` on success
+    pub fn record_deletion(&self, full_name: &str, error: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO deletions (run_id, full_name, deleted_at, success, error)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                self.run_id,
+                full_name,
+                chrono::Utc::now().to_rfc3339(),
+                error.is_none(),
+                error,
+            ],
+        )?;
+
+        Ok(())
+    }
+}
@@ -1,12 +1,41 @@
 mod analyzer;
 mod cli;
+mod doctor;
+mod error;
 mod github;
+mod history;
+mod report;
 
-use analyzer::ForkAnalyzer;
+use analyzer::{AnalyzerOptions, ForkAnalyzer, ForkInfo, ForkKind, KeepRule};
 use anyhow::{Context, Result};
-use clap::Parser;
-use cli::CliInterface;
-use github::GitHubClient;
+use clap::builder::RangedU64ValueParser;
+use clap::{Parser, Subcommand};
+use cli::{CliInterface, OnError, Preselect, PromptDefault, SummarySort};
+use error::DisforkError;
+use github::{AccountType, Deletion, GitHubApi, GitHubClient, InstallationToken};
+use history::History;
+use report::{
+    DeletionFailure, Event, ForkRecord, LogFormat, OutputFormat, RunSummary, StatusFilter,
+};
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::watch;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Cooldown before each batch after the first with --batch-size
+const BATCH_COOLDOWN_SECS: u64 = 5;
+
+/// With --cooldown-scale, the cooldown is the base plus some seconds per repository,
+/// up to the max
+const SCALED_COOLDOWN_BASE_SECS: u64 = 10;
+const SCALED_COOLDOWN_SECS_PER_REPO: u64 = 1;
+const SCALED_COOLDOWN_MAX_SECS: u64 = 120;
 
 #[derive(Parser, Debug)]
 #[command(name = "DisFork")]
@@ -14,6 +43,9 @@ use github::GitHubClient;
 #[command(version = clap::crate_version!())]
 #[command(about = clap::crate_description!(), long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// GitHub access token (overrides GitHub App authorization)
     #[arg(long, env = "GITHUB_TOKEN")]
     github_token: Option<String>,
@@ -26,35 +58,471 @@ struct Args {
     #[arg(long, default_value = "Iv23licpLWlZABwjnLK7")]
     app_client_id: String,
 
+    /// Give up waiting for device flow authorization after this many seconds
+    #[arg(long, value_name = "SECS")]
+    auth_timeout: Option<u64>,
+
+    /// Authenticate as a GitHub App installation with this app ID (for CI)
+    #[arg(long, requires_all = ["app_private_key", "installation_id"])]
+    app_id: Option<u64>,
+
+    /// PEM private key of the GitHub App given by --app-id
+    #[arg(long, value_name = "PATH", requires = "app_id")]
+    app_private_key: Option<PathBuf>,
+
+    /// Installation of the GitHub App given by --app-id to act as
+    #[arg(long, requires = "app_id")]
+    installation_id: Option<u64>,
+
     /// GitHub user or organization to scan (defaults to authenticated user)
     #[arg(long)]
     account: Option<String>,
 
+    /// Also scan these users or organizations in the same run, e.g. an org alongside your
+    /// account, to spot forks of the same upstream in several of them (repeatable)
+    #[arg(long = "also-account", value_name = "ACCOUNT")]
+    also_accounts: Vec<String>,
+
+    /// Force listing repositories as a user or organization, skipping detection
+    #[arg(long, value_enum, default_value_t = AccountType::Auto)]
+    account_type: AccountType,
+
     /// Skip interactive selection and delete all useless forks
     #[arg(long)]
     auto: bool,
 
+    /// Which repositories are selected by default in the interactive prompt
+    #[arg(long, value_enum, default_value_t = Preselect::Useless)]
+    preselect: Preselect,
+
+    /// With --auto, only delete forks last pushed more than this many days ago, never those
+    /// without a known push date
+    #[arg(long, value_name = "DAYS")]
+    max_age_for_auto: Option<u32>,
+
+    /// Preselect the forks named in this file (one full name per line) instead
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["auto", "delete_from_stdin"])]
+    select_file: Option<PathBuf>,
+
+    /// Order of the repositories listed before deletion
+    #[arg(long, value_enum, default_value_t = SummarySort::Name)]
+    summary_sort: SummarySort,
+
     /// Number of parallel HTTP requests
-    #[arg(long, default_value_t = 8)]
+    #[arg(long, default_value_t = 8, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
     parallel: usize,
 
-    /// Skip analyzing repos with more than this many branches
+    /// Max retries for rate limited requests, waiting as long as GitHub asks or backing off
+    /// exponentially, and for deletions failed with a server error
+    #[arg(long, default_value_t = 3)]
+    max_retries: u32,
+
+    /// Compare only the default branch and the N most recently committed branches of
+    /// forks with more branches than this, instead of every branch. Forks over
+    /// --max-branches are sampled this way too instead of skipped
+    #[arg(long, value_name = "N")]
+    limit_branches_per_fork: Option<usize>,
+
+    /// Compare every branch to report the total commits ahead, instead of stopping at
+    /// the first branch ahead (approximate: shared commits count once per branch)
+    #[arg(long)]
+    total_ahead: bool,
+
+    /// Stop listing the account's repositories after this many, forks or not
+    #[arg(long, value_name = "N")]
+    max_repos: Option<usize>,
+
+    /// Analyze likely useless forks (empty, archived, stale, small) first
+    #[arg(long)]
+    prioritize: bool,
+
+    /// Don't count branches as ahead whose commits landed upstream through a squash or
+    /// rebase merge of a pull request from them, costs a request per branch ahead
+    #[arg(long)]
+    detect_squash_merged: bool,
+
+    /// Quick triage by push timestamps alone, without comparing any branch: forks not
+    /// pushed to since forking or since upstream's last push are useless
+    #[arg(long, conflicts_with = "auto")]
+    fast: bool,
+
+    /// Don't count commits by bots (`*[bot]` authors) as ahead, merge commits never count
+    #[arg(long)]
+    ignore_bot_commits: bool,
+
+    /// Compare every branch instead of stopping at the first branch ahead, and add each
+    /// branch's ahead/behind to JSON/JSONL/SQLite output
+    #[arg(long)]
+    detailed_branches: bool,
+
+    /// Reuse compare results within a run for forks at the same commit, most effective
+    /// with many forks of one upstream
+    #[arg(long)]
+    compare_cache: bool,
+
+    /// Max parallel repo lookups while fetching fork parents before the analysis
+    #[arg(long, default_value_t = 8, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    parent_fetch_concurrency: usize,
+
+    /// Max parallel compare requests for a single fork, uncapped by default. Forks with few
+    /// branches get analyzed sooner next to forks with many, but the whole run takes
+    /// longer as requests sit idle once only branch-heavy forks are left
+    #[arg(long, value_name = "N", value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    per_fork_compare_limit: Option<usize>,
+
+    /// Skip analyzing repos with more than this many branches, unless
+    /// --limit-branches-per-fork is given
     #[arg(long, default_value_t = 20)]
     max_branches: usize,
 
+    /// Consider a fork useless only if this branch is fully merged upstream, ignoring
+    /// all other branches (repeatable, all must be merged). Required branches are checked
+    /// even if they match --ignore-branches
+    #[arg(long = "require-merged", value_name = "BRANCH")]
+    require_merged: Vec<String>,
+
+    /// Don't count branches matching this glob (`*` matches anything) when judging
+    /// usefulness (repeatable, replaces the defaults, `--ignore-branches ''` for none).
+    /// Has no effect with --require-merged, which only looks at the required branches
+    #[arg(
+        long,
+        value_name = "GLOB",
+        default_values_t = ["dependabot/*", "renovate/*", "gh-pages"].map(String::from)
+    )]
+    ignore_branches: Vec<String>,
+
+    /// Keep forks that any of the account's other repos use as a submodule, costs a
+    /// request per non-fork repo
+    #[arg(long)]
+    check_submodules: bool,
+
+    /// Executable run for each useless fork with its JSON record and repository on stdin,
+    /// printing `keep` keeps the fork, e.g. to encode org-specific policies. Any language
+    /// works, there's no embedded interpreter; scripts can only keep forks, never mark more
+    /// as useless
+    #[arg(long, value_name = "PATH")]
+    rule_script: Option<PathBuf>,
+
+    /// Keep only one fork per fork network, deleting useless duplicates
+    #[arg(long)]
+    dedupe_network: bool,
+
+    /// DANGEROUS: consider forks useless even if their default branch is ahead, as long as
+    /// no other branch is. Commits on the default branch are lost when deleting, so this
+    /// requires --yes as an explicit acknowledgment
+    #[arg(long, requires = "yes")]
+    ignore_default_branch_only_ahead: bool,
+
+    /// Of several useless forks of the same upstream, keep only the most recently pushed
+    #[arg(long)]
+    delete_older_duplicates: bool,
+
+    /// Also select forks kept in sync with upstream by automation for deletion. They're
+    /// useless either way, but left out of --auto, --print-selected and the preselection
+    /// without this, as the automation may still need them
+    #[arg(long)]
+    delete_mirrors: bool,
+
+    /// Only consider forks of archived upstreams for deletion
+    #[arg(long, conflicts_with = "keep_archived_upstream")]
+    only_archived_upstream: bool,
+
+    /// Keep forks of archived upstreams
+    #[arg(long)]
+    keep_archived_upstream: bool,
+
+    /// Compare forks of forks to their immediate parent instead of the network's source repo
+    #[arg(long)]
+    prefer_parent: bool,
+
+    /// Only analyze forks tagged with any of these topics (repeatable or comma-separated)
+    #[arg(long = "include-topics", value_name = "TOPIC", value_delimiter = ',')]
+    include_topics: Vec<String>,
+
+    /// Keep forks tagged with any of these topics, e.g. `keep` (repeatable or comma-separated)
+    #[arg(long = "exclude-topics", value_name = "TOPIC", value_delimiter = ',')]
+    exclude_topics: Vec<String>,
+
+    /// Only delete forks whose fork network originates from this account, unlike the
+    /// immediate parent this is the root of the fork chain
+    #[arg(long, value_name = "LOGIN")]
+    source_only: Option<String>,
+
+    /// Only consider forks of this upstream repository, e.g. rust-lang/rust (repeatable)
+    #[arg(long, value_name = "OWNER/NAME")]
+    fork_of: Vec<String>,
+
+    /// Match --fork-of against the fork network's source instead of the parent
+    #[arg(long, requires = "fork_of")]
+    fork_of_source: bool,
+
+    /// Keep forks whose default branch is protected
+    #[arg(long)]
+    keep_if_default_branch_protected: bool,
+
+    /// Keep forks pushed to within this many days, whatever their branches
+    #[arg(long, value_name = "DAYS")]
+    keep_recent: Option<u32>,
+
+    /// Keep forks with more than this many stars or watchers
+    #[arg(long, value_name = "COUNT")]
+    keep_starred_over: Option<u32>,
+
+    /// Don't keep forks that published their own releases or have tags upstream lacks
+    #[arg(long)]
+    ignore_releases: bool,
+
+    /// Keep forks with Actions workflow runs within this many days
+    #[arg(long, value_name = "DAYS")]
+    ci_activity_days: Option<u32>,
+
+    /// Keep forks with any GitHub deployment, costs a request per useless fork
+    #[arg(long)]
+    keep_with_deployments: bool,
+
+    /// Consider forks of your own repos for deletion
+    #[arg(long)]
+    include_self_forks: bool,
+
+    /// Consider forks that have been forked themselves for deletion, which detaches their
+    /// forks from the network
+    #[arg(long)]
+    include_forked: bool,
+
+    /// Unarchive archived repositories right before deleting them, for accounts whose
+    /// policies refuse to delete archived repositories
+    #[arg(long)]
+    unarchive_before_delete: bool,
+
+    /// Also list repositories that aren't forks but have no commits at all
+    #[arg(long)]
+    include_empty: bool,
+
+    /// Other accounts you control, forks of their repos count as self-forks (repeatable)
+    #[arg(long = "own-account")]
+    own_accounts: Vec<String>,
+
+    /// Don't ask for confirmation before deleting
+    #[arg(long)]
+    yes: bool,
+
+    /// Magic word that must be passed to delete unattended with --auto --yes
+    #[arg(long)]
+    confirm_word: Option<String>,
+
+    /// Expected value of --confirm-word
+    #[arg(long, env = "DISFORK_CONFIRM_WORD", default_value = "DELETE")]
+    expected_confirm_word: String,
+
+    /// Delete in batches of this size, each preceded by a short cooldown
+    #[arg(long)]
+    batch_size: Option<usize>,
+
+    /// Page the list of repositories to delete through $PAGER even if it fits on screen,
+    /// or in runs that don't page by default (--auto, --delete-from-stdin, --watch)
+    #[arg(long, conflicts_with = "no_pager")]
+    pager: bool,
+
+    /// Never page the list of repositories to delete
+    #[arg(long)]
+    no_pager: bool,
+
+    /// Only ask for confirmation when deleting at least this many repositories
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    confirm_threshold: usize,
+
+    /// Scale the cooldown before deleting with the number of repositories (up to 2 minutes)
+    #[arg(long)]
+    cooldown_scale: bool,
+
+    /// Answer of the deletion confirmation when pressing Enter
+    #[arg(long, value_enum, default_value_t = PromptDefault::Keep)]
+    prompt_default: PromptDefault,
+
+    /// What to do when a deletion fails
+    #[arg(long, value_enum, default_value_t = OnError::Continue)]
+    on_error: OnError,
+
+    /// In dry runs, check that the token may delete each selected repository
+    #[arg(long)]
+    dry_run_delete_check: bool,
+
+    /// Look up each deleted repository again afterwards to check it's really gone
+    #[arg(long)]
+    verify_after_delete: bool,
+
     /// Don't actually delete anything
     #[arg(long)]
     dry_run: bool,
+
+    /// Print the analysis to stdout in this format and exit, instead of the interactive UI;
+    /// `events` keeps the interactive UI on stderr and streams progress events to stdout
+    #[arg(long, value_enum, default_value_t = OutputFormat::Human)]
+    output: OutputFormat,
+
+    /// Print a JSON summary of the run (counts, deleted repos, failures) to stdout at the
+    /// end, the interactive UI stays on stderr
+    #[arg(long, conflicts_with = "output")]
+    summary_json: bool,
+
+    /// Only list forks with commits upstream doesn't have, on any branch
+    #[arg(
+        long,
+        group = "status_filter",
+        conflicts_with_all = ["auto", "delete_from_stdin", "watch"]
+    )]
+    only_ahead: bool,
+
+    /// Only list forks without commits ahead whose default branch is behind upstream
+    #[arg(
+        long,
+        group = "status_filter",
+        conflicts_with_all = ["auto", "delete_from_stdin", "watch"]
+    )]
+    only_behind: bool,
+
+    /// Only list forks without commits ahead whose default branch is at upstream's commit
+    #[arg(
+        long,
+        group = "status_filter",
+        conflicts_with_all = ["auto", "delete_from_stdin", "watch"]
+    )]
+    only_synced: bool,
+
+    /// Pretty-print JSON output (default when stdout is a terminal)
+    #[arg(long, conflicts_with = "compact")]
+    pretty: bool,
+
+    /// Print JSON output on a single line (default when stdout is piped)
+    #[arg(long)]
+    compact: bool,
+
+    /// Append this run's analysis and deletion outcomes to a SQLite database
+    #[arg(long, value_name = "PATH")]
+    sqlite: Option<PathBuf>,
+
+    /// Only report kept forks and why they're kept, then exit
+    #[arg(long, conflicts_with = "print_selected")]
+    report_only_kept: bool,
+
+    /// Print how verdicts changed between two --output json/jsonl exports and exit
+    #[arg(long, num_args = 2, value_names = ["OLD", "NEW"])]
+    plan_diff: Vec<PathBuf>,
+
+    /// Print full names of useless forks to stdout, one per line, and exit; not with
+    /// `--output events`
+    #[arg(long, requires = "dry_run")]
+    print_selected: bool,
+
+    /// Delete forks whose full names are read from stdin, one per line
+    #[arg(long, conflicts_with_all = ["auto", "print_selected", "watch"])]
+    delete_from_stdin: bool,
+
+    /// Also log events to stderr in this format
+    #[arg(long, value_enum, default_value_t = LogFormat::Human)]
+    log_format: LogFormat,
+
+    /// Show each fork's branch count in the selection list
+    #[arg(long)]
+    count_branches_in_summary: bool,
+
+    /// Show links to each fork and its upstream in lists and JSON output
+    #[arg(long)]
+    show_urls: bool,
+
+    /// Show a static message instead of an animated spinner
+    #[arg(long)]
+    no_spinner: bool,
+
+    /// Spinner animation interval in milliseconds
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 100,
+        conflicts_with = "no_spinner"
+    )]
+    tick_ms: u64,
+
+    /// Testing only: fail every Nth API request as rate limited, to exercise the retries
+    #[arg(long, hide = true, value_name = "EVERY_N")]
+    simulate_rate_limit: Option<u64>,
+
+    /// Re-scan every interval (e.g. 6h) until SIGINT/SIGTERM, deleting only with --auto --yes
+    #[arg(long, value_parser = humantime::parse_duration)]
+    watch: Option<Duration>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Diagnose token, connectivity, and account access without changing anything
+    Doctor,
+    /// Delete branches merged into or even with upstream from the forks worth keeping
+    Branches,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
-    let cli = CliInterface::new();
+    let spinner_tick = (!args.no_spinner).then(|| Duration::from_millis(args.tick_ms.max(1)));
+    let cli = CliInterface::new()
+        .with_spinner_tick(spinner_tick)
+        .with_branch_counts(args.count_branches_in_summary)
+        .with_upstreams(!args.fork_of.is_empty())
+        .with_urls(args.show_urls)
+        .with_mirrors_preselected(args.delete_mirrors);
+
+    if args.log_format == LogFormat::Json {
+        tracing_subscriber::fmt()
+            .json()
+            .with_writer(std::io::stderr)
+            .with_max_level(tracing::Level::INFO)
+            .init();
+    } else {
+        // Retries and failed checks aren't shown otherwise, deletions are
+        let warnings = Targets::new()
+            .with_target("disfork::github", tracing::Level::WARN)
+            .with_target("disfork::analyzer", tracing::Level::WARN);
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .without_time()
+            .with_target(false)
+            .finish()
+            .with(warnings)
+            .init();
+    }
 
     cli.show_welcome()?;
 
-    let token = if let Some(token) = args.github_token {
+    if let [old, new] = args.plan_diff.as_slice() {
+        return report::plan_diff(old, new);
+    }
+
+    if let Some(Command::Doctor) = args.command {
+        return doctor::run(&cli, args.github_token.clone(), args.account.as_deref()).await;
+    }
+
+    // Guard against misconfigured unattended runs before doing any work
+    if args.auto
+        && args.yes
+        && !args.dry_run
+        && args.confirm_word.as_deref() != Some(args.expected_confirm_word.as_str())
+    {
+        anyhow::bail!(
+            "Refusing to delete with --auto --yes without --confirm-word {}",
+            args.expected_confirm_word
+        );
+    }
+
+    // The event stream owns stdout
+    if args.output == OutputFormat::Events && args.print_selected {
+        anyhow::bail!("--print-selected can't be combined with --output events");
+    }
+
+    let mut installation_token = mint_installation_token(&args).await?;
+    let token = if let Some(installation) = &installation_token {
+        cli.show_info("Authenticated as GitHub App installation")?;
+        installation.token.clone()
+    } else if let Some(token) = args.github_token.clone() {
         cli.show_info("Using GITHUB_TOKEN from environment")?;
         token
     } else {
@@ -89,6 +557,7 @@ async fn main() -> Result<()> {
             &device_code.device_code,
             device_code.interval,
             device_code.expires_in,
+            args.auth_timeout,
         )
         .await
         .context("Failed to get access token")?;
@@ -97,118 +566,949 @@ async fn main() -> Result<()> {
         token
     };
 
-    let client = GitHubClient::new(token, args.parallel).context("Failed to create GitHub client")?;
-    let target_account = if let Some(account) = args.account {
+    let mut client = build_client(&args, token)?;
+    if let Some(every) = args.simulate_rate_limit {
+        cli.show_error(&format!(
+            "Testing hook active: 1 in every {} API requests will fail as rate limited",
+            every
+        ))?;
+    }
+    let target_account = if let Some(account) = args.account.clone() {
         account
     } else {
         client.current_user().await?
     };
 
+    if let Some(Command::Branches) = args.command {
+        return match prune_branches(&args, &cli, &client, &target_account).await {
+            Ok(()) => Ok(()),
+            Err(e) => Err(explain_rate_limit(&client, e).await),
+        };
+    }
+
+    let Some(interval) = args.watch else {
+        let started = Instant::now();
+        return match run(&args, &cli, &client, &target_account, None).await {
+            Ok(summary) => report_run(&args, &target_account, started, &summary),
+            Err(e) => Err(explain_rate_limit(&client, e).await),
+        };
+    };
+
+    let (stop_tx, mut stop_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        if shutdown_signal().await.is_ok() {
+            let _ = stop_tx.send(true);
+        }
+    });
+
+    loop {
+        // Installation tokens only live for an hour
+        if installation_token
+            .as_ref()
+            .is_some_and(InstallationToken::expires_soon)
+        {
+            match mint_installation_token(&args).await {
+                Ok(Some(fresh)) => {
+                    client = build_client(&args, fresh.token.clone())?;
+                    installation_token = Some(fresh);
+                }
+                Ok(None) => {}
+                Err(e) => cli.show_error(&format!("{:#}", e))?,
+            }
+        }
+
+        // Keep watching even if a single cycle fails, e.g. on network hiccups
+        let started = Instant::now();
+        match run(&args, &cli, &client, &target_account, Some(&stop_rx)).await {
+            Ok(summary) => report_run(&args, &target_account, started, &summary)?,
+            Err(e) => {
+                let e = explain_rate_limit(&client, e).await;
+                cli.show_error(&format!("{:#}", e))?;
+            }
+        }
+        if *stop_rx.borrow() {
+            break;
+        }
+
+        cli.show_info(&format!(
+            "Next scan in {}, press Ctrl-C to stop",
+            humantime::format_duration(interval)
+        ))?;
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = stop_rx.changed() => break,
+        }
+    }
+
+    cli.show_info("Stopped watching")?;
+    Ok(())
+}
+
+/// Reports a finished run on stdout, if asked to
+fn report_run(args: &Args, account: &str, started: Instant, summary: &RunSummary) -> Result<()> {
+    if args.output == OutputFormat::Events {
+        Event::Done { summary }.emit()?;
+    }
+    if args.summary_json {
+        report::write_summary(summary, account, started.elapsed())?;
+    }
+    Ok(())
+}
+
+fn build_client(args: &Args, token: String) -> Result<GitHubClient> {
+    let client = GitHubClient::new(token, args.parallel, args.max_retries)
+        .context("Failed to create GitHub client")?
+        .with_simulated_rate_limit(args.simulate_rate_limit);
+    Ok(client)
+}
+
+/// Mints an installation token if authenticating as a GitHub App installation
+async fn mint_installation_token(args: &Args) -> Result<Option<InstallationToken>> {
+    let (Some(app_id), Some(key_path), Some(installation_id)) =
+        (args.app_id, &args.app_private_key, args.installation_id)
+    else {
+        return Ok(None);
+    };
+
+    let key = tokio::fs::read(key_path)
+        .await
+        .with_context(|| format!("Failed to read {}", key_path.display()))?;
+    let token = GitHubClient::mint_installation_token(app_id, &key, installation_id)
+        .await
+        .context("Failed to mint installation token")?;
+    Ok(Some(token))
+}
+
+/// Resolves on SIGINT, or SIGTERM on Unix
+async fn shutdown_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
+
+/// Whether the fork is deleted without picking it by hand, mirrors only with
+/// --delete-mirrors
+fn auto_selects(args: &Args, info: &ForkInfo) -> bool {
+    info.is_useless() && (args.delete_mirrors || info.kind != ForkKind::Mirror)
+}
+
+/// Resolves once SIGINT/SIGTERM asked to stop watching, never without `--watch`
+async fn stop_requested(stop: Option<&watch::Receiver<bool>>) {
+    if let Some(stop) = stop
+        && stop.clone().wait_for(|&stop| stop).await.is_ok()
+    {
+        return;
+    }
+    std::future::pending().await
+}
+
+fn is_stopped(stop: Option<&watch::Receiver<bool>>) -> bool {
+    stop.is_some_and(|stop| *stop.borrow())
+}
+
+fn show_untouched(cli: &CliInterface, remaining: &[&ForkInfo]) -> Result<()> {
+    cli.show_info(&format!("{} repositories left untouched:", remaining.len()))?;
+    for info in remaining {
+        cli.show_list_item(info.full_name())?;
+    }
+    Ok(())
+}
+
+/// Scan, analyze, and delete once. With `stop`, a stop request aborts the deletions
+/// still pending.
+async fn run(
+    args: &Args,
+    cli: &CliInterface,
+    client: &GitHubClient,
+    target_account: &str,
+    stop: Option<&watch::Receiver<bool>>,
+) -> Result<RunSummary> {
+    // Nobody's around to pick repos or confirm in watch mode
+    let unattended = args.watch.is_some();
+    let dry_run = args.dry_run || (unattended && !(args.auto && args.yes));
+    let events = args.output == OutputFormat::Events;
+    let mut summary = RunSummary::default();
+
     let spinner = cli.create_spinner("Fetching repositories...")?;
-    let repos = client
-        .list_repos(&target_account)
+    let mut repos = client
+        .list_repos(target_account, args.account_type, args.max_repos)
         .await
         .context("Failed to list repositories")?;
-    let forks: Vec<_> = repos
+    for account in &args.also_accounts {
+        let more = client
+            .list_repos(account, AccountType::Auto, args.max_repos)
+            .await
+            .with_context(|| format!("Failed to list repositories of {}", account))?;
+        repos.truncated |= more.truncated;
+        repos.items.extend(more.items);
+    }
+    if repos.truncated {
+        spinner.suspend(|| {
+            cli.show_error(&format!(
+                "Stopped listing after {} repositories, narrow down with filters or raise --max-repos",
+                repos.items.len()
+            ))
+        })?;
+    }
+    let (forks, sources): (Vec<_>, Vec<_>) = repos
+        .items
         .into_iter()
-        .filter(|r| r.fork.unwrap_or(false))
-        .collect();
+        .partition(|r| r.fork.unwrap_or(false));
 
-    if forks.is_empty() {
+    if forks.is_empty() && !args.include_empty {
         cli.show_success("No fork repositories found!")?;
-        return Ok(());
+        return Ok(summary);
+    }
+    if events {
+        for fork in &forks {
+            let full_name = fork.full_name.as_deref().unwrap_or(&fork.name);
+            Event::RepoListed { full_name }.emit()?;
+        }
     }
 
     spinner.finish_with_message(format!("Found {} fork repositories", forks.len()));
 
-    let analyzer = ForkAnalyzer::new(client.clone(), args.max_branches);
+    let analyzer = ForkAnalyzer::new(client.clone(), analyzer_options(args));
+    let mut forks = if args.include_topics.is_empty() {
+        forks
+    } else {
+        let mut included = Vec::new();
+        for fork in forks {
+            let topics = analyzer.topics(&fork).await?;
+            if analyzer::has_any_topic(&topics, &args.include_topics) {
+                included.push(fork);
+            }
+        }
+        included
+    };
+
+    if args.prioritize {
+        analyzer::prioritize(&mut forks);
+    }
+
+    let spinner = cli.create_spinner("Fetching parent repositories...")?;
+    let forks = analyzer.fetch_parents(forks).await?;
+    spinner.finish_and_clear();
     let pb = cli.create_progress_bar(forks.len() as u64, "Analyzing")?;
 
-    let mut tasks = tokio::task::JoinSet::new();
+    if events {
+        Event::AnalysisStarted { forks: forks.len() }.emit()?;
+    }
+    let mut results = analyzer.analyze_all(forks);
+    let mut fork_infos = Vec::new();
+    while let Some(result) = results.recv().await {
+        let info = result?;
+        tracing::info!(
+            repo = info.full_name(),
+            useless = info.is_useless(),
+            verdict = ?info.verdict,
+            kind = ?info.kind,
+            ahead_by = info.divergence.as_ref().map(|d| d.ahead_by),
+            behind_by = info.divergence.as_ref().map(|d| d.behind_by),
+            "analyzed fork"
+        );
+        if events {
+            Event::fork_analyzed(&info).emit()?;
+        }
+        fork_infos.push(info);
+        pb.inc(1);
+    }
+    pb.finish_with_message("Analysis complete");
+    if args.include_empty {
+        let spinner = cli.create_spinner("Looking for empty repositories...")?;
+        for repo in &sources {
+            if let Some(info) = analyzer.analyze_empty(repo.clone()).await? {
+                if events {
+                    Event::fork_analyzed(&info).emit()?;
+                }
+                fork_infos.push(info);
+            }
+        }
+        spinner.finish_and_clear();
+    }
+    // Analyses finish in any order, keep the listing and exports stable between runs
+    fork_infos.sort_by(|a, b| a.full_name().cmp(b.full_name()));
 
-    for fork in forks {
-        let analyzer = analyzer.clone();
-        let pb = pb.clone();
+    for info in fork_infos
+        .iter()
+        .filter(|info| info.kind == ForkKind::Anomalous)
+    {
+        cli.show_error(&format!(
+            "{} lists itself as its parent, skipped comparing",
+            info.full_name()
+        ))?;
+    }
 
-        tasks.spawn(async move {
-            let result = analyzer.analyze_fork(fork).await;
-            pb.inc(1);
-            result
+    if args.dedupe_network {
+        analyzer::dedupe_network(&mut fork_infos);
+    }
+    if args.delete_older_duplicates {
+        analyzer::keep_newest_duplicates(&mut fork_infos);
+    }
+    analyzer::flag_duplicates(&mut fork_infos);
+    if args.check_submodules {
+        let spinner = cli.create_spinner("Checking submodules of your other repositories...")?;
+        let mut referenced = HashSet::new();
+        for repo in &sources {
+            let Some(owner) = repo.owner.as_ref() else {
+                continue;
+            };
+            referenced.extend(client.submodule_repos(&owner.login, &repo.name).await?);
+        }
+        spinner.finish_and_clear();
+        analyzer::keep_listed(&mut fork_infos, &referenced, KeepRule::Submodule);
+    }
+    if let Some(script) = &args.rule_script {
+        apply_rule_script(script, &mut fork_infos).await?;
+    }
+    let accounts = std::iter::once((target_account, args.account_type)).chain(
+        args.also_accounts
+            .iter()
+            .map(|account| (account.as_str(), AccountType::Auto)),
+    );
+    for (account, account_type) in accounts {
+        // Deleting a pinned repo silently drops it from the profile
+        let pinned = client.pinned_repos(account).await;
+        analyzer::keep_looked_up(&mut fork_infos, pinned, KeepRule::Pinned)?;
+        // Consumers may still pull what was published from a fork
+        let package_repos = client.package_repos(account, account_type).await;
+        analyzer::keep_looked_up(&mut fork_infos, package_repos, KeepRule::Packages)?;
+    }
+
+    summary.forks = fork_infos.len();
+    summary.survivors = fork_infos
+        .iter()
+        .map(|info| info.full_name().to_string())
+        .collect();
+    summary.useless = fork_infos.iter().filter(|info| info.is_useless()).count();
+
+    let history = match &args.sqlite {
+        Some(path) => {
+            let mut history = History::start(path, target_account)?;
+            history.record_analysis(&fork_infos, args.detailed_branches)?;
+            Some(history)
+        }
+        None => None,
+    };
+
+    let status_filter = if args.only_ahead {
+        Some(StatusFilter::Ahead)
+    } else if args.only_behind {
+        Some(StatusFilter::Behind)
+    } else if args.only_synced {
+        Some(StatusFilter::Synced)
+    } else {
+        None
+    };
+    // Only narrows down what's listed and exported, the flags conflict with every way of
+    // deleting without picking forks by hand
+    if let Some(filter) = status_filter {
+        fork_infos.retain(|info| filter.matches(info));
+    }
+
+    if !args.fork_of.is_empty() {
+        fork_infos.retain(|info| {
+            let upstream = if args.fork_of_source {
+                info.repo.source.as_ref()
+            } else {
+                info.repo.parent.as_ref()
+            };
+            upstream
+                .and_then(|upstream| upstream.full_name.as_deref())
+                .is_some_and(|name| args.fork_of.iter().any(|f| f.eq_ignore_ascii_case(name)))
         });
     }
 
-    let mut fork_infos = Vec::new();
-    while let Some(result) = tasks.join_next().await {
-        fork_infos.push(result??);
+    if args.report_only_kept {
+        fork_infos.retain(|info| !info.is_useless());
+    }
+
+    if !matches!(args.output, OutputFormat::Human | OutputFormat::Events) {
+        let pretty = args.pretty || (!args.compact && std::io::stdout().is_terminal());
+        report::write(
+            &fork_infos,
+            args.output,
+            pretty,
+            args.show_urls,
+            args.detailed_branches,
+        )?;
+        return Ok(summary);
+    }
+
+    // Never goes on to deleting, whatever the output. The event stream already carries
+    // each kept fork's reason.
+    if args.report_only_kept {
+        if args.output == OutputFormat::Human {
+            for info in &fork_infos {
+                let reason = info.keep_reason().unwrap_or_default();
+                println!("{} - {}", info.full_name(), reason);
+            }
+        }
+        return Ok(summary);
+    }
+
+    if args.print_selected {
+        for info in fork_infos.iter().filter(|info| auto_selects(args, info)) {
+            println!("{}", info.full_name());
+        }
+        return Ok(summary);
     }
-    pb.finish_with_message("Analysis complete");
 
     // 选择要删除的仓库
-    let selections = if args.auto {
+    let selections = if args.delete_from_stdin {
+        read_stdin_selection(&fork_infos).await?
+    } else if args.auto || unattended {
+        // Unattended runs may be limited to forks that have been dead for a while
+        let pushed_before = args
+            .max_age_for_auto
+            .map(|days| chrono::Utc::now() - chrono::TimeDelta::days(i64::from(days)));
         fork_infos
             .iter()
             .enumerate()
-            .filter(|(_, info)| info.is_useless)
+            .filter(|(_, info)| auto_selects(args, info))
+            .filter(|(_, info)| match (pushed_before, info.repo.pushed_at) {
+                (Some(cutoff), Some(pushed)) => pushed < cutoff,
+                // An unknown age can't pass the guard
+                (Some(_), None) => false,
+                (None, _) => true,
+            })
             .map(|(i, _)| i)
             .collect()
     } else {
-        cli.select_repos_to_delete(&fork_infos)?
+        let preselected = match &args.select_file {
+            Some(path) => {
+                let input = tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                let (selections, unknown) = match_full_names(&fork_infos, &input);
+                for name in unknown {
+                    cli.show_error(&format!("{} is not an analyzed fork, ignored", name))?;
+                }
+                Some(selections)
+            }
+            None => None,
+        };
+        cli.select_repos_to_delete(&fork_infos, args.preselect, preselected.as_deref())?
     };
 
     if selections.is_empty() {
         cli.show_info("No repositories selected for deletion")?;
-        return Ok(());
+        return Ok(summary);
     }
 
-    let selected_repos: Vec<_> = selections.iter().map(|&i| &fork_infos[i]).collect();
+    let mut selected_repos: Vec<_> = selections.iter().map(|&i| &fork_infos[i]).collect();
+    summary.selected = selected_repos.len();
+    match args.summary_sort {
+        SummarySort::Name => selected_repos.sort_by(|a, b| a.full_name().cmp(b.full_name())),
+        SummarySort::Risk => {
+            selected_repos.sort_by(|a, b| b.risk_score().total_cmp(&a.risk_score()))
+        }
+        SummarySort::Ahead => selected_repos.sort_by_key(|info| std::cmp::Reverse(info.ahead_by())),
+    }
 
     // 显示将要删除的仓库
     cli.show_info(&format!(
         "Selected {} repositories for deletion:",
         selected_repos.len()
     ))?;
-    for info in &selected_repos {
-        println!("  - {}", info.full_name());
-    }
+    // Nobody may be around to quit the pager of an unattended or scripted run
+    let pager = match (args.pager, args.no_pager) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ if args.auto || args.delete_from_stdin || unattended => Some(false),
+        _ => None,
+    };
+    cli.show_deletion_list(&selected_repos, pager)?;
 
-    if args.dry_run {
+    if dry_run {
+        if args.dry_run_delete_check {
+            check_delete_permissions(cli, &selected_repos)?;
+        }
         cli.show_info("Dry run mode - no repositories will be deleted")?;
-        return Ok(());
+        return Ok(summary);
     }
 
     // 确认删除
     let is_batch = selected_repos.len() > 1;
-    if !cli.confirm_deletion(selected_repos.len(), is_batch)? {
+    let needs_confirm = !args.yes && selected_repos.len() >= args.confirm_threshold;
+    if needs_confirm && !cli.confirm_deletion(&selected_repos, is_batch, args.prompt_default)? {
         cli.show_info("Deletion cancelled")?;
-        return Ok(());
+        return Ok(summary);
     }
 
     // 冷静期
-    let cooldown = if is_batch { 20 } else { 5 };
-    cli.show_cooldown(cooldown, is_batch).await?;
+    let cooldown = if args.cooldown_scale {
+        let count = selected_repos.len() as u64;
+        (SCALED_COOLDOWN_BASE_SECS + SCALED_COOLDOWN_SECS_PER_REPO * count)
+            .min(SCALED_COOLDOWN_MAX_SECS)
+    } else if is_batch {
+        20
+    } else {
+        5
+    };
+    if !cli
+        .show_cooldown(cooldown, is_batch, stop_requested(stop))
+        .await?
+    {
+        cli.show_info("Deletion cancelled")?;
+        return Ok(summary);
+    }
 
     // 删除仓库
-    let pb = cli.create_progress_bar(selected_repos.len() as u64, "Deleting")?;
-    for info in selected_repos {
+    let batch_size = args.batch_size.unwrap_or(selected_repos.len()).max(1);
+    let batch_count = selected_repos.len().div_ceil(batch_size);
+    let mut deleted = Vec::new();
+    if events {
+        Event::DeletionStarted {
+            repos: selected_repos.len(),
+        }
+        .emit()?;
+    }
+    'batches: for (batch_index, batch) in selected_repos.chunks(batch_size).enumerate() {
+        // Every further batch gets its own chance to Ctrl-C
+        if batch_index > 0
+            && !cli
+                .show_cooldown(BATCH_COOLDOWN_SECS, true, stop_requested(stop))
+                .await?
+        {
+            cli.show_info("Deletion stopped")?;
+            show_untouched(cli, &selected_repos[batch_index * batch_size..])?;
+            break;
+        }
+
+        let message = if batch_count > 1 {
+            format!("Deleting batch {}/{}", batch_index + 1, batch_count)
+        } else {
+            "Deleting".to_string()
+        };
+        let pb = cli.create_progress_bar(batch.len() as u64, &message)?;
+        for (offset, info) in batch.iter().enumerate() {
+            if is_stopped(stop) {
+                pb.abandon();
+                cli.show_info("Deletion stopped")?;
+                show_untouched(cli, &selected_repos[batch_index * batch_size + offset..])?;
+                break 'batches;
+            }
+            let owner = info
+                .owner_login()
+                .with_context(|| format!("{} is missing owner information", info.full_name()))?;
+            let repo_name = info.repo.name.as_str();
+
+            let unarchived = if args.unarchive_before_delete && info.is_archived() {
+                client.unarchive_repo(owner, repo_name).await
+            } else {
+                Ok(())
+            };
+            let result = match unarchived {
+                Ok(()) => client.delete_repo(owner, repo_name).await,
+                // Deleted elsewhere in the meantime, like `delete_repo` reports it
+                Err(DisforkError::NotFound(_)) => Ok(Deletion::AlreadyGone),
+                Err(e) => Err(e),
+            };
+            if let Some(history) = &history {
+                let error = result.as_ref().err().map(ToString::to_string);
+                history.record_deletion(info.full_name(), error.as_deref())?;
+            }
+
+            if events {
+                Event::Deleted {
+                    full_name: info.full_name(),
+                    ok: result.is_ok(),
+                    error: result.as_ref().err().map(ToString::to_string),
+                }
+                .emit()?;
+            }
+
+            match result {
+                Ok(Deletion::Deleted) => {
+                    tracing::info!(repo = info.full_name(), "deleted fork");
+                    cli.show_success(&format!("Deleted {}", info.full_name()))?;
+                    summary.deleted_repos.push(info.full_name().to_string());
+                    summary.gone(info.full_name());
+                    deleted.push(*info);
+                }
+                Ok(Deletion::AlreadyGone) => {
+                    summary.already_gone += 1;
+                    summary.gone(info.full_name());
+                    tracing::info!(repo = info.full_name(), "fork already deleted");
+                    cli.show_info(&format!(
+                        "{} was already deleted, skipped",
+                        info.full_name()
+                    ))?;
+                }
+                Err(e) => {
+                    summary.failed += 1;
+                    summary.failures.push(DeletionFailure {
+                        full_name: info.full_name().to_string(),
+                        error: e.to_string(),
+                    });
+                    tracing::error!(repo = info.full_name(), error = %e, "failed to delete fork");
+                    cli.show_error(&format!("Failed to delete {}: {}", info.full_name(), e))?;
+
+                    let keep_going = match args.on_error {
+                        OnError::Continue => true,
+                        OnError::Abort => false,
+                        OnError::Prompt => pb.suspend(|| cli.confirm_continue())?,
+                    };
+                    if !keep_going {
+                        pb.abandon();
+                        show_untouched(
+                            cli,
+                            &selected_repos[batch_index * batch_size + offset + 1..],
+                        )?;
+                        anyhow::bail!(
+                            "Deletion aborted after failing to delete {}",
+                            info.full_name()
+                        );
+                    }
+                }
+            }
+
+            pb.inc(1);
+        }
+        pb.finish_with_message("Deletion complete");
+
+        if batch_count > 1 {
+            cli.show_info(&format!(
+                "Batch {}/{} done, {}/{} repositories processed",
+                batch_index + 1,
+                batch_count,
+                batch_index * batch_size + batch.len(),
+                selected_repos.len()
+            ))?;
+        }
+    }
+
+    if args.verify_after_delete {
+        verify_deletions(cli, client, &deleted).await?;
+    }
+    if summary.already_gone > 0 {
+        cli.show_info(&format!(
+            "{} repositories were already deleted",
+            summary.already_gone
+        ))?;
+    }
+    if !is_stopped(stop) {
+        cli.show_success("All done!")?;
+    }
+
+    summary.deleted = deleted.len();
+    Ok(summary)
+}
+
+/// Offers to delete the branches of kept forks that upstream already has, for the
+/// `branches` command
+async fn prune_branches(
+    args: &Args,
+    cli: &CliInterface,
+    client: &GitHubClient,
+    target_account: &str,
+) -> Result<()> {
+    let spinner = cli.create_spinner("Fetching repositories...")?;
+    let repos = client
+        .list_repos(target_account, args.account_type, args.max_repos)
+        .await
+        .context("Failed to list repositories")?;
+    let forks: Vec<_> = repos
+        .items
+        .into_iter()
+        .filter(|r| r.fork.unwrap_or(false))
+        .collect();
+    spinner.finish_with_message(format!("Found {} fork repositories", forks.len()));
+
+    // Every branch needs comparing, not just enough to tell the fork is worth keeping
+    let analyzer = ForkAnalyzer::new(
+        client.clone(),
+        AnalyzerOptions {
+            detailed_branches: true,
+            ..analyzer_options(args)
+        },
+    );
+    let spinner = cli.create_spinner("Fetching parent repositories...")?;
+    let forks = analyzer.fetch_parents(forks).await?;
+    spinner.finish_and_clear();
+    let pb = cli.create_progress_bar(forks.len() as u64, "Analyzing")?;
+    let mut results = analyzer.analyze_all(forks);
+    let mut fork_infos = Vec::new();
+    while let Some(result) = results.recv().await {
+        fork_infos.push(result?);
+        pb.inc(1);
+    }
+    pb.finish_with_message("Analysis complete");
+    fork_infos.sort_by(|a, b| a.full_name().cmp(b.full_name()));
+
+    // Useless forks are better deleted as a whole
+    let branches: Vec<(&ForkInfo, &str)> = fork_infos
+        .iter()
+        .filter(|info| !info.is_useless())
+        .flat_map(|info| {
+            info.prunable_branches()
+                .into_iter()
+                .map(move |branch| (info, branch))
+        })
+        .collect();
+    if branches.is_empty() {
+        cli.show_success("No merged branches found in the kept forks!")?;
+        return Ok(());
+    }
+
+    let names: Vec<(&str, &str)> = branches
+        .iter()
+        .map(|(info, branch)| (info.full_name(), *branch))
+        .collect();
+    let selections = if args.auto {
+        (0..branches.len()).collect()
+    } else {
+        cli.select_branches_to_delete(&names)?
+    };
+    if selections.is_empty() {
+        cli.show_info("No branches selected for deletion")?;
+        return Ok(());
+    }
+
+    cli.show_info(&format!(
+        "Selected {} branches for deletion:",
+        selections.len()
+    ))?;
+    for &i in &selections {
+        let (repo, branch) = names[i];
+        cli.show_list_item(&format!("{} {}", repo, branch))?;
+    }
+    if args.dry_run {
+        cli.show_info("Dry run mode - no branches will be deleted")?;
+        return Ok(());
+    }
+    if !args.yes && !cli.confirm_branch_deletion(selections.len(), args.prompt_default)? {
+        cli.show_info("Deletion cancelled")?;
+        return Ok(());
+    }
+
+    let pb = cli.create_progress_bar(selections.len() as u64, "Deleting")?;
+    for &i in &selections {
+        let (info, branch) = branches[i];
         let owner = info
             .owner_login()
             .with_context(|| format!("{} is missing owner information", info.full_name()))?;
-        let repo_name = info.repo.name.as_str();
-
-        match client.delete_repo(owner, repo_name).await {
+        match client.delete_branch(owner, &info.repo.name, branch).await {
             Ok(_) => {
-                cli.show_success(&format!("Deleted {}", info.full_name()))?;
+                tracing::info!(repo = info.full_name(), branch, "deleted branch");
+                cli.show_success(&format!("Deleted {} {}", info.full_name(), branch))?;
             }
             Err(e) => {
-                cli.show_error(&format!("Failed to delete {}: {}", info.full_name(), e))?;
+                tracing::error!(repo = info.full_name(), branch, error = %e, "failed to delete branch");
+                cli.show_error(&format!(
+                    "Failed to delete {} {}: {}",
+                    info.full_name(),
+                    branch,
+                    e
+                ))?;
             }
         }
-
         pb.inc(1);
     }
     pb.finish_with_message("Deletion complete");
-
     cli.show_success("All done!")?;
+    Ok(())
+}
+
+fn analyzer_options(args: &Args) -> AnalyzerOptions {
+    AnalyzerOptions {
+        max_branches: args.max_branches,
+        limit_branches_per_fork: args.limit_branches_per_fork,
+        total_ahead: args.total_ahead || args.summary_sort == SummarySort::Ahead,
+        compare_cache: args.compare_cache,
+        detailed_branches: args.detailed_branches,
+        detect_squash_merged: args.detect_squash_merged,
+        ignore_bot_commits: args.ignore_bot_commits,
+        fast: args.fast,
+        ignore_default_branch_only_ahead: args.ignore_default_branch_only_ahead,
+        parent_fetch_concurrency: args.parent_fetch_concurrency,
+        per_fork_compare_limit: args.per_fork_compare_limit,
+        require_merged: args.require_merged.clone(),
+        ignore_branches: args.ignore_branches.clone(),
+        only_archived_upstream: args.only_archived_upstream,
+        keep_archived_upstream: args.keep_archived_upstream,
+        prefer_parent: args.prefer_parent,
+        exclude_topics: args.exclude_topics.clone(),
+        keep_if_default_branch_protected: args.keep_if_default_branch_protected,
+        keep_recent: args.keep_recent,
+        keep_starred_over: args.keep_starred_over,
+        ignore_releases: args.ignore_releases,
+        ci_activity_days: args.ci_activity_days,
+        keep_with_deployments: args.keep_with_deployments,
+        own_accounts: args.own_accounts.clone(),
+        source_only: args.source_only.clone(),
+        include_self_forks: args.include_self_forks,
+        include_forked: args.include_forked,
+    }
+}
+
+/// Asks the rule script about each useless fork, see `--rule-script`. A narrower take on
+/// scripted policies than an embedded Rhai or Lua engine returning a verdict or score: no
+/// scripting engine to depend on, and it can only veto deletions, so a buggy script can't
+/// get more forks deleted.
+async fn apply_rule_script(script: &Path, fork_infos: &mut [ForkInfo]) -> Result<()> {
+    for info in fork_infos.iter_mut().filter(|info| info.is_useless()) {
+        let input = serde_json::to_vec(&serde_json::json!({
+            "fork": ForkRecord::from(&*info).with_urls(info),
+            "repository": &info.repo,
+        }))?;
 
+        let mut child = tokio::process::Command::new(script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Failed to start rule script {}", script.display()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // The script may decide without reading its input
+            match stdin.write_all(&input).await {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e.into()),
+                _ => {}
+            }
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "Rule script failed for {} ({})",
+                info.full_name(),
+                output.status
+            );
+        }
+
+        if String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .eq_ignore_ascii_case("keep")
+        {
+            info.keep(KeepRule::Script);
+        }
+    }
+    Ok(())
+}
+
+/// Reports whether deleting each repo would be allowed, going by the admin permission
+/// GitHub reports for the token, as there's no way to try a delete without doing it
+fn check_delete_permissions(cli: &CliInterface, selected: &[&ForkInfo]) -> Result<()> {
+    let mut failing = 0;
+    for info in selected {
+        match info.repo.permissions.as_ref().map(|p| p.admin) {
+            Some(true) => cli.show_success(&format!("{}: would succeed", info.full_name()))?,
+            Some(false) => {
+                failing += 1;
+                cli.show_error(&format!("{}: would fail (no admin)", info.full_name()))?;
+            }
+            None => cli.show_info(&format!(
+                "{}: unknown, GitHub didn't report permissions",
+                info.full_name()
+            ))?,
+        }
+    }
+
+    if failing > 0 {
+        cli.show_error(&format!(
+            "{} of {} repositories would fail to delete",
+            failing,
+            selected.len()
+        ))?;
+    } else {
+        cli.show_success("All permission checks passed")?;
+    }
     Ok(())
 }
+
+/// Looks up each deleted repo again and flags those that still exist
+async fn verify_deletions(
+    cli: &CliInterface,
+    client: &GitHubClient,
+    deleted: &[&ForkInfo],
+) -> Result<()> {
+    // Give GitHub a moment to settle before asking again
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let mut survivors = 0;
+    for info in deleted {
+        let Some(owner) = info.owner_login() else {
+            continue;
+        };
+        match client.get_repo(owner, &info.repo.name).await {
+            Ok(_) => {
+                survivors += 1;
+                cli.show_error(&format!("{} was NOT actually deleted", info.full_name()))?;
+            }
+            Err(DisforkError::NotFound(_)) => {}
+            Err(e @ DisforkError::RateLimited { .. }) => {
+                cli.show_error(&format!("Stopped verifying deletions: {}", e))?;
+                return Ok(());
+            }
+            Err(e) => {
+                cli.show_error(&format!("Failed to verify {}: {}", info.full_name(), e))?;
+            }
+        }
+    }
+
+    if survivors == 0 {
+        cli.show_success(&format!("Verified {} deletions", deleted.len()))?;
+    }
+    Ok(())
+}
+
+/// Adds when the quota resets to errors caused by an exhausted rate limit
+async fn explain_rate_limit(client: &GitHubClient, err: anyhow::Error) -> anyhow::Error {
+    let rate_limited = err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<DisforkError>(),
+            Some(DisforkError::RateLimited { .. })
+        )
+    });
+    if !rate_limited {
+        return err;
+    }
+
+    match client.rate_limit_reset().await {
+        Ok(Some(reset_at)) => err.context(DisforkError::RateLimitExhausted { reset_at }),
+        _ => err,
+    }
+}
+
+/// Indices of the analyzed forks named on stdin, one full name per line
+async fn read_stdin_selection(fork_infos: &[ForkInfo]) -> Result<Vec<usize>> {
+    let input = tokio::task::spawn_blocking(|| std::io::read_to_string(std::io::stdin()))
+        .await
+        .context("Failed to read stdin")??;
+
+    let (selections, unknown) = match_full_names(fork_infos, &input);
+    if let Some(name) = unknown.first() {
+        anyhow::bail!("{} is not an analyzed fork", name);
+    }
+
+    Ok(selections)
+}
+
+/// Indices of the analyzed forks named in `input`, one full name per line, and the
+/// names that matched none
+fn match_full_names<'a>(fork_infos: &[ForkInfo], input: &'a str) -> (Vec<usize>, Vec<&'a str>) {
+    let mut selections = Vec::new();
+    let mut unknown = Vec::new();
+    for name in input.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        match fork_infos
+            .iter()
+            .position(|info| info.full_name().eq_ignore_ascii_case(name))
+        {
+            Some(index) if !selections.contains(&index) => selections.push(index),
+            Some(_) => {}
+            None => unknown.push(name),
+        }
+    }
+
+    (selections, unknown)
+}
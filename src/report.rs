@@ -0,0 +1,335 @@
+use crate::analyzer::{BranchResult, BranchSample, ForkInfo, ForkKind, KeepRule, Verdict};
+use crate::github::{CompareStatus, Comparison};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Interactive terminal UI
+    Human,
+    /// A single JSON array of analyzed forks
+    Json,
+    /// One JSON object per analyzed fork per line
+    Jsonl,
+    /// Newline-delimited JSON progress events alongside the interactive UI, for front-ends
+    Events,
+}
+
+/// How events are logged to stderr besides the interactive output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Nothing but the interactive output
+    Human,
+    /// One JSON object per event (analysis verdicts, deletions), for log aggregators
+    Json,
+}
+
+/// Divergence from upstream to restrict the listed forks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusFilter {
+    /// Any branch has commits upstream doesn't, whether or not it's also behind
+    Ahead,
+    /// No branch ahead, and the default branch only misses upstream commits
+    Behind,
+    /// No branch ahead, and the default branch at the same commit as upstream
+    Synced,
+}
+
+impl StatusFilter {
+    /// Forks whose default branch wasn't compared never match
+    pub fn matches(self, info: &ForkInfo) -> bool {
+        let Some(divergence) = &info.divergence else {
+            return false;
+        };
+        match self {
+            Self::Ahead => info.has_commits_ahead,
+            Self::Behind => !info.has_commits_ahead && divergence.status == CompareStatus::Behind,
+            Self::Synced => {
+                !info.has_commits_ahead && divergence.status == CompareStatus::Identical
+            }
+        }
+    }
+}
+
+/// Machine-readable view of a [`ForkInfo`]
+#[derive(Debug, Serialize)]
+pub struct ForkRecord<'a> {
+    pub full_name: &'a str,
+    pub useless: bool,
+    pub verdict: Verdict,
+    pub reasons: Vec<String>,
+    pub kind: ForkKind,
+    pub divergence: Option<&'a Comparison>,
+    pub network: Option<&'a str>,
+    pub self_fork: bool,
+    pub archived: bool,
+    pub duplicate_of: Option<&'a str>,
+    pub branch_count: Option<usize>,
+    pub branch_sample: Option<BranchSample>,
+    pub total_ahead: Option<i64>,
+    pub kept_by: Option<KeepRule>,
+    pub keep_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_url: Option<&'a str>,
+    #[serde(rename = "branches", skip_serializing_if = "Option::is_none")]
+    pub branch_results: Option<&'a [BranchResult]>,
+}
+
+impl<'a> From<&'a ForkInfo> for ForkRecord<'a> {
+    fn from(info: &'a ForkInfo) -> Self {
+        Self {
+            full_name: info.full_name(),
+            useless: info.is_useless(),
+            verdict: info.verdict,
+            reasons: info.reasons(),
+            kind: info.kind,
+            divergence: info.divergence.as_ref(),
+            network: info.network.as_deref(),
+            self_fork: info.is_self_fork,
+            archived: info.is_archived(),
+            duplicate_of: info.duplicate_of.as_deref(),
+            branch_count: info.branch_count,
+            branch_sample: info.branch_sample,
+            total_ahead: info.total_ahead,
+            kept_by: info.kept_by,
+            keep_reason: info.keep_reason(),
+            branch_results: None,
+            url: None,
+            upstream_url: None,
+        }
+    }
+}
+
+impl<'a> ForkRecord<'a> {
+    /// Adds links to the fork and its parent on GitHub
+    pub fn with_urls(self, info: &'a ForkInfo) -> Self {
+        Self {
+            url: info.html_url(),
+            upstream_url: info.parent_html_url(),
+            ..self
+        }
+    }
+
+    /// Adds each compared branch, only exported with --detailed-branches
+    pub fn with_branches(self, info: &'a ForkInfo) -> Self {
+        Self {
+            branch_results: info.branch_results.as_deref(),
+            ..self
+        }
+    }
+}
+
+/// Writes the analysis to stdout, `pretty` only affects [`OutputFormat::Json`]
+/// as JSON Lines must stay one record per line
+pub fn write(
+    fork_infos: &[ForkInfo],
+    format: OutputFormat,
+    pretty: bool,
+    urls: bool,
+    branches: bool,
+) -> Result<()> {
+    let records: Vec<ForkRecord> = fork_infos
+        .iter()
+        .map(|info| {
+            let record = ForkRecord::from(info);
+            let record = if urls { record.with_urls(info) } else { record };
+            if branches {
+                record.with_branches(info)
+            } else {
+                record
+            }
+        })
+        .collect();
+    let mut stdout = std::io::stdout().lock();
+
+    match format {
+        OutputFormat::Human | OutputFormat::Events => {}
+        OutputFormat::Json if pretty => {
+            serde_json::to_writer_pretty(&mut stdout, &records)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer(&mut stdout, &records)?;
+            writeln!(stdout)?;
+        }
+        OutputFormat::Jsonl => {
+            for record in &records {
+                serde_json::to_writer(&mut stdout, record)?;
+                writeln!(stdout)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Version of the [`Event`] schema, bumped on incompatible changes
+const EVENTS_VERSION: u32 = 1;
+
+/// Outcome of a finished run
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RunSummary {
+    pub forks: usize,
+    pub useless: usize,
+    pub selected: usize,
+    pub deleted: usize,
+    pub already_gone: usize,
+    pub failed: usize,
+    pub deleted_repos: Vec<String>,
+    pub failures: Vec<DeletionFailure>,
+    /// Analyzed forks still there after the run: kept, not selected or failed to delete
+    pub survivors: Vec<String>,
+}
+
+impl RunSummary {
+    /// Takes a fork that's gone, deleted or not by this run, off the survivors
+    pub fn gone(&mut self, full_name: &str) {
+        self.survivors.retain(|name| name != full_name);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeletionFailure {
+    pub full_name: String,
+    pub error: String,
+}
+
+/// Prints the run's summary to stdout as a single JSON object for `--summary-json`,
+/// the interactive UI stays on stderr
+pub fn write_summary(summary: &RunSummary, account: &str, duration: Duration) -> Result<()> {
+    #[derive(Serialize)]
+    struct Summary<'a> {
+        account: &'a str,
+        duration_secs: f64,
+        #[serde(flatten)]
+        summary: &'a RunSummary,
+    }
+
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer(
+        &mut stdout,
+        &Summary {
+            account,
+            duration_secs: duration.as_secs_f64(),
+            summary,
+        },
+    )?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+/// Progress of a run as emitted by `--output events`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    RepoListed {
+        full_name: &'a str,
+    },
+    AnalysisStarted {
+        forks: usize,
+    },
+    ForkAnalyzed {
+        full_name: &'a str,
+        status: Verdict,
+        reason: String,
+    },
+    DeletionStarted {
+        repos: usize,
+    },
+    Deleted {
+        full_name: &'a str,
+        ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Done {
+        summary: &'a RunSummary,
+    },
+}
+
+impl<'a> Event<'a> {
+    pub fn fork_analyzed(info: &'a ForkInfo) -> Self {
+        Self::ForkAnalyzed {
+            full_name: info.full_name(),
+            status: info.verdict,
+            reason: info.reasons().join("; "),
+        }
+    }
+
+    /// Writes the event to stdout as a single line, tagged with the schema version
+    pub fn emit(&self) -> Result<()> {
+        #[derive(Serialize)]
+        struct Versioned<'a> {
+            v: u32,
+            #[serde(flatten)]
+            event: &'a Event<'a>,
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        serde_json::to_writer(
+            &mut stdout,
+            &Versioned {
+                v: EVENTS_VERSION,
+                event: self,
+            },
+        )?;
+        writeln!(stdout)?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+/// The part of a [`ForkRecord`] needed to diff two exports
+#[derive(Debug, Deserialize)]
+struct PlannedFork {
+    full_name: String,
+    useless: bool,
+}
+
+/// Reads a `--output json` or `jsonl` export, keyed by full name
+fn read_plan(path: &Path) -> Result<BTreeMap<String, bool>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let forks: Vec<PlannedFork> = match serde_json::from_str(&content) {
+        Ok(forks) => forks,
+        Err(_) => content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .with_context(|| format!("{} is not a JSON or JSONL export", path.display()))?,
+    };
+
+    Ok(forks
+        .into_iter()
+        .map(|fork| (fork.full_name, fork.useless))
+        .collect())
+}
+
+/// Prints how the verdicts changed between two exports: `+` newly useless,
+/// `-` useful again, `x` gone from the newer export
+pub fn plan_diff(old: &Path, new: &Path) -> Result<()> {
+    let old = read_plan(old)?;
+    let new = read_plan(new)?;
+    let mut stdout = std::io::stdout().lock();
+
+    for (name, &useless) in &new {
+        match old.get(name) {
+            Some(false) | None if useless => writeln!(stdout, "+ {} (newly useless)", name)?,
+            Some(true) if !useless => writeln!(stdout, "- {} (useful again)", name)?,
+            _ => {}
+        }
+    }
+    for name in old.keys().filter(|name| !new.contains_key(*name)) {
+        writeln!(stdout, "x {} (gone)", name)?;
+    }
+
+    Ok(())
+}